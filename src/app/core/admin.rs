@@ -0,0 +1,166 @@
+//! Operational endpoints, behind the `ADMIN_TOKEN` bearer token.
+
+use anyhow::anyhow;
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::{request::Parts, StatusCode};
+use axum::response::Json;
+use axum::{routing::get, Router};
+use axum_extra::extract::WithRejection;
+use controllers::extractors::bearer;
+use custom_errors::app_rejection::{AppRejection, WithJsonRejection};
+use custom_errors::err_response::{res, ErrResponse, JsonKind, JsonResult};
+use environment::ENV;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{event, level_filters::LevelFilter, Level};
+use types::api;
+use utils::LogFilter;
+
+pub fn router() -> Router {
+    Router::new().route("/log-filter", get(log_filter).put(set_log_filter))
+}
+
+/// Rejects requests without the admin bearer token. Without a configured
+/// token, the admin endpoints do not exist.
+pub struct Admin;
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Admin {
+    type Rejection = AppRejection<JsonKind>;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let Some(expected) = &ENV.config.admin.token else {
+            let message = "Not found".to_string();
+            return Err(AppRejection::new(message, StatusCode::NOT_FOUND));
+        };
+        match bearer(&parts.headers) {
+            Some(token) if constant_time_eq(token, expected) => Ok(Self),
+            _ => Err(AppRejection::new(
+                "Invalid or missing admin token".to_string(),
+                StatusCode::UNAUTHORIZED,
+            )),
+        }
+    }
+}
+
+/// Compares without returning early, so that timing does not leak how much
+/// of the token was right. Digests are compared rather than the tokens, so
+/// that the length of the token does not leak either.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    Sha256::digest(a)
+        .iter()
+        .zip(Sha256::digest(b).iter())
+        .fold(0, |acc, (x, y)| acc | (x ^ y))
+        == 0
+}
+
+#[derive(Serialize)]
+struct FilterState {
+    severity: String,
+    directives: String,
+    /// The effective `EnvFilter`, once both are combined.
+    filter: String,
+}
+
+impl FilterState {
+    fn new(log_filter: &LogFilter) -> anyhow::Result<Self> {
+        Ok(Self {
+            severity: log_filter.severity.to_string(),
+            directives: log_filter.directives.clone(),
+            filter: log_filter.env_filter()?.to_string(),
+        })
+    }
+}
+
+/// Fields left out keep their current value.
+#[derive(Deserialize)]
+struct FilterUpdate {
+    severity: Option<String>,
+    directives: Option<String>,
+}
+
+async fn log_filter(_: Admin) -> JsonResult {
+    let current = utils::log_filter()
+        .ok_or_else(|| anyhow!("Logging is not initialized"))?;
+    res(Json(api::Response::success(FilterState::new(&current)?)))
+}
+
+async fn set_log_filter(
+    _: Admin,
+    WithRejection(Json(update), _): WithJsonRejection<Json<FilterUpdate>>,
+) -> JsonResult {
+    let current = utils::log_filter()
+        .ok_or_else(|| anyhow!("Logging is not initialized"))?;
+    let severity = match update.severity {
+        Some(severity) => severity.parse::<LevelFilter>().map_err(|e| {
+            ErrResponse::new(
+                format!("Invalid severity: {e}"),
+                StatusCode::BAD_REQUEST,
+                None,
+            )
+        })?,
+        None => current.severity,
+    };
+    let log_filter = LogFilter {
+        severity,
+        directives: update.directives.unwrap_or(current.directives),
+    };
+    if let Err(e) = utils::set_log_filter(log_filter.clone()) {
+        return Err(ErrResponse::new(
+            format!("Invalid log filter: {e}"),
+            StatusCode::BAD_REQUEST,
+            None,
+        ));
+    }
+
+    let filter = FilterState::new(&log_filter)?;
+    event!(Level::WARN, "Log filter changed to `{}`", filter.filter);
+    res(Json(api::Response::success(filter)))
+}
+
+/// Re-reads `LOG_SEVERITY` and `RUST_LOG` on every SIGHUP. Variables of the
+/// process environment cannot change, so only `.env` and the configuration
+/// files are worth editing beforehand.
+///
+/// # Panics
+///
+/// Will panic if it fails to install the signal handler.
+#[cfg(unix)]
+pub async fn reload_on_sighup() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = signal(SignalKind::hangup()).unwrap_or_else(|e| {
+        panic!("Failed to install SIGHUP handler! Error: {e}")
+    });
+    while hangups.recv().await.is_some() {
+        let result = reload_log_filter();
+        match result {
+            Ok(()) => event!(Level::WARN, "Log filter reloaded on SIGHUP"),
+            Err(e) => event!(
+                Level::ERROR,
+                "Failed to reload log filter on SIGHUP! Error: {e}"
+            ),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn reload_log_filter() -> anyhow::Result<()> {
+    let config = ENV.reload()?;
+    utils::set_log_filter(LogFilter {
+        severity: config.logging.severity,
+        directives: config.logging.directives,
+    })
+}
+
+#[test]
+fn test() {
+    assert!(constant_time_eq("token", "token"));
+    assert!(!constant_time_eq("token", "tokem"));
+    assert!(!constant_time_eq("token", "token2"));
+    assert!(!constant_time_eq("", "token"));
+}
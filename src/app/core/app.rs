@@ -2,14 +2,17 @@ use anyhow::anyhow;
 use axum::{
     body::Body,
     error_handling::HandleErrorLayer,
-    http::{Response, StatusCode},
+    http::{Request, Response, StatusCode},
+    middleware,
     response::{Html, IntoResponse},
     Router,
 };
+use controllers::extractors::{commit_or_rollback, sessions, Sessions};
 use controllers::Routes;
 use custom_errors::err_response::{ErrResponse, HtmlKind, JsonKind};
 use environment::ENV;
-use std::{borrow::Cow, time::Duration};
+use repositories::PgSessionStore;
+use std::borrow::Cow;
 use tower::{
     limit::ConcurrencyLimitLayer, load_shed::LoadShedLayer, BoxError,
     ServiceBuilder,
@@ -17,16 +20,33 @@ use tower::{
 use tower_http::{
     services::ServeDir, timeout::TimeoutLayer, trace::TraceLayer,
 };
+use tracing::{info_span, Span};
+use utils::RequestId;
 use views::not_found;
 
+use crate::{admin, metrics, probes};
+
 pub fn app() -> Router {
+    let dynamic = Router::new()
+        .configure_routes()
+        .nest("/admin", admin::router())
+        // Ends the request transaction, see `Tx`
+        .layer(middleware::from_fn(commit_or_rollback))
+        // Saves the request session once the transaction ended, and
+        // outside of it, see `Session`
+        .layer(middleware::from_fn_with_state(
+            Sessions::new(PgSessionStore),
+            sessions,
+        ));
+
     Router::new()
-        // Serve static files from the `assets` directory
+        // Serve static files from the `assets` directory, without loading
+        // sessions, so that they are served even while the DB is down
         .nest_service(
             "/assets",
             ServeDir::new(ENV.workspace_dir.join("assets")),
         )
-        .configure_routes()
+        .merge(dynamic)
         .fallback(fallback)
         // Insert here all layers that might fail. Make sure to treat the error in `handle_error`.
         // Axum's philosophy is to ensure layers cannot fail, so when using something like a tower layer that
@@ -37,9 +57,30 @@ pub fn app() -> Router {
                 LoadShedLayer::new(),
             )),
         )
-        .layer(ConcurrencyLimitLayer::new(1024))
-        .layer(TimeoutLayer::new(Duration::from_secs(15)))
-        .layer(TraceLayer::new_for_http())
+        .layer(ConcurrencyLimitLayer::new(ENV.config.limits.concurrency))
+        .layer(TimeoutLayer::new(ENV.config.limits.request_timeout))
+        .layer(TraceLayer::new_for_http().make_span_with(make_span))
+        // Outside of the `TraceLayer`, so that the request span has the id
+        .layer(middleware::from_fn(utils::request_id))
+        .layer(middleware::from_fn(metrics::track))
+        // Merged last, so that none of the layers above apply
+        .merge(probes::router())
+        .merge(metrics::router())
+}
+
+/// The request span, joining the trace of the caller when it sent a
+/// `traceparent` header.
+fn make_span(request: &Request<Body>) -> Span {
+    let request_id = request.extensions().get::<RequestId>();
+    let span = info_span!(
+        "request",
+        request_id = request_id.map(|id| id.0.as_str()),
+        method = %request.method(),
+        uri = %request.uri(),
+        version = ?request.version(),
+    );
+    utils::set_parent_from(&span, request.headers());
+    span
 }
 
 async fn fallback() -> Result<impl IntoResponse, ErrResponse<HtmlKind>> {
@@ -49,6 +90,7 @@ async fn fallback() -> Result<impl IntoResponse, ErrResponse<HtmlKind>> {
 async fn handle_error(error: BoxError) -> Response<Body> {
     // If server is overloaded, immediately returns a 503 without further processing the request
     if error.is::<tower::load_shed::error::Overloaded>() {
+        metrics::record_load_shed();
         return (
             StatusCode::SERVICE_UNAVAILABLE,
             Cow::from("Service is overloaded, try again later."),
@@ -0,0 +1,73 @@
+use clap::{Parser, Subcommand};
+use environment::Options;
+use std::path::Path;
+
+/// Command-line interface of the application.
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Applies `config/<PROFILE>.toml` over `config/default.toml`.
+    /// Defaults to `APP_ENV`.
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Overrides a setting, taking precedence over every other layer.
+    /// E.g.: `--set server.port=8080`.
+    #[arg(long = "set", value_name = "KEY=VALUE", global = true)]
+    pub overrides: Vec<String>,
+
+    /// Defaults to `serve`.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Starts the HTTP server.
+    Serve,
+    /// Inspects the effective configuration.
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Manages the database migrations embedded from `src/app/migrations`.
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Prints every setting, with secrets redacted, and where it came from.
+    Dump,
+    /// Validates the configuration, without binding a port or touching the
+    /// database. Exits with a non-zero code if it is invalid.
+    Check,
+}
+
+impl Cli {
+    /// The configuration layers selected by the command line.
+    pub fn options(&self, workspace_dir: &'static Path) -> Options {
+        Options {
+            profile: self.profile.clone(),
+            overrides: self.overrides.clone(),
+            ..Options::new(workspace_dir)
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum MigrateCommand {
+    /// Applies every pending migration.
+    Up,
+    /// Reverts applied migrations, only the latest one by default.
+    Down {
+        /// Reverts every migration newer than this version instead.
+        #[arg(long)]
+        target: Option<i64>,
+    },
+    /// Lists every migration, and whether it was applied.
+    Status,
+    /// Creates a new reversible migration in `src/app/migrations`.
+    New {
+        /// Short description, e.g. `create_users`.
+        name: String,
+    },
+}
@@ -0,0 +1,46 @@
+use std::process::ExitCode;
+
+use environment::{Environment, Options};
+
+use crate::cli::ConfigCommand;
+
+pub fn run(command: &ConfigCommand, options: &Options) -> ExitCode {
+    let environment = match Environment::load(options) {
+        Ok(environment) => environment,
+        Err(e) => {
+            eprintln!("Invalid configuration! {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match command {
+        ConfigCommand::Dump => dump(&environment),
+        ConfigCommand::Check => println!("Configuration is valid."),
+    }
+    ExitCode::SUCCESS
+}
+
+fn dump(environment: &Environment) {
+    let profile = environment.profile.as_deref().unwrap_or("(none)");
+    println!("# Profile: {profile}");
+
+    let lines: Vec<(String, String)> = environment
+        .resolved
+        .iter()
+        .map(|resolved| {
+            let setting = resolved.redacted().map_or_else(
+                || format!("# {} is not set", resolved.key),
+                |value| format!("{} = {value:?}", resolved.key),
+            );
+            (
+                setting,
+                format!("# {}, from {}", resolved.env, resolved.layer),
+            )
+        })
+        .collect();
+
+    let width = lines.iter().map(|(s, _)| s.len()).max().unwrap_or(0);
+    for (setting, origin) in lines {
+        println!("{setting:width$}  {origin}");
+    }
+}
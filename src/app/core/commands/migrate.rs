@@ -0,0 +1,122 @@
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::{fs, process::ExitCode};
+
+use anyhow::Context;
+use environment::{Options, ENV};
+use repositories::Database;
+use sqlx::migrate::MigrationType;
+
+use crate::cli::MigrateCommand;
+use crate::migrations::{self, MIGRATOR};
+
+pub async fn run(command: &MigrateCommand, options: &Options) -> ExitCode {
+    let result = match command {
+        // Only needs the sources, not the configuration nor the DB
+        MigrateCommand::New { name } => new(options.workspace_dir, name),
+        MigrateCommand::Up => with_database(options, up()).await,
+        MigrateCommand::Down { target } => {
+            with_database(options, down(*target)).await
+        }
+        MigrateCommand::Status => with_database(options, status()).await,
+    };
+
+    result.map_or_else(
+        |e| {
+            eprintln!("{e:#}");
+            ExitCode::FAILURE
+        },
+        |()| ExitCode::SUCCESS,
+    )
+}
+
+/// Where migrations are, in the sources. They are embedded in the binary at
+/// build time, see `MIGRATOR`.
+fn migrations_dir(workspace_dir: &Path) -> PathBuf {
+    workspace_dir.join("src/app/migrations")
+}
+
+/// Runs `command` once the configuration is loaded, then disconnects.
+async fn with_database(
+    options: &Options,
+    command: impl Future<Output = anyhow::Result<()>>,
+) -> anyhow::Result<()> {
+    ENV.try_init(options).context("Invalid configuration")?;
+    let result = command.await;
+    Database::disconnect().await;
+    result
+}
+
+async fn up() -> anyhow::Result<()> {
+    let pool = Database::try_get_pool().await?;
+    let pending = migrations::status(pool)
+        .await?
+        .iter()
+        .filter(|s| !s.applied)
+        .count();
+    MIGRATOR.run(pool).await?;
+    println!("Applied {pending} migration(s).");
+    Ok(())
+}
+
+async fn down(target: Option<i64>) -> anyhow::Result<()> {
+    let pool = Database::try_get_pool().await?;
+    let applied: Vec<i64> = migrations::status(pool)
+        .await?
+        .iter()
+        .filter(|s| s.applied)
+        .map(|s| s.migration.version)
+        .collect();
+
+    // Reverting everything newer than the second to last applied migration
+    // only reverts the last one.
+    let target = target
+        .unwrap_or_else(|| applied.iter().rev().nth(1).copied().unwrap_or(0));
+    let reverted = applied.iter().filter(|v| **v > target).count();
+    MIGRATOR.undo(pool, target).await?;
+    println!("Reverted {reverted} migration(s).");
+    Ok(())
+}
+
+async fn status() -> anyhow::Result<()> {
+    let statuses = migrations::status(Database::try_get_pool().await?).await?;
+    if statuses.is_empty() {
+        let dir = migrations_dir(ENV.workspace_dir);
+        println!("No migrations were embedded from {}.", dir.display());
+    }
+    for status in statuses {
+        let state = match (status.applied, status.changed) {
+            (true, true) => "Changed",
+            (true, false) => "Applied",
+            (false, _) => "Pending",
+        };
+        let migration = status.migration;
+        println!("{state:8} {} {}", migration.version, migration.description);
+    }
+    Ok(())
+}
+
+fn new(workspace_dir: &Path, name: &str) -> anyhow::Result<()> {
+    let description: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let version = chrono::Utc::now().format("%Y%m%d%H%M%S");
+
+    let dir = migrations_dir(workspace_dir);
+    for kind in [MigrationType::ReversibleUp, MigrationType::ReversibleDown] {
+        let path =
+            dir.join(format!("{version}_{description}{}", kind.suffix()));
+        fs::write(&path, kind.file_content())
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        println!("Created {}", path.display());
+    }
+    Ok(())
+}
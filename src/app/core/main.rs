@@ -1,11 +1,25 @@
+use clap::Parser;
 use environment::{get_workspace_dir, ENV};
-use repositories::Database;
-use std::net::SocketAddr;
+use repositories::{Database, PgSessionStore};
+use std::{net::SocketAddr, process::ExitCode};
 use tokio::net::TcpListener;
 use tracing::{event, Level};
 
+mod cli;
+use cli::{Cli, Command};
+
+mod commands;
+
+mod migrations;
+
+mod metrics;
+
+mod probes;
+
+mod admin;
+
 mod on_shutdown;
-use on_shutdown::with_graceful_shutdown;
+use on_shutdown::serve_with_graceful_shutdown;
 
 mod app;
 use app::app;
@@ -15,32 +29,80 @@ use app::app;
 static GLOBAL: jemallocator::Jemalloc = jemallocator::Jemalloc;
 
 #[tokio::main]
-async fn main() {
-    let workspace_dir = get_workspace_dir();
-    dotenv::from_filename(workspace_dir.join(".env")).ok();
-    ENV.init(workspace_dir);
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let options = cli.options(get_workspace_dir());
 
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            ENV.init(&options);
+            serve().await
+        }
+        Command::Config(command) => commands::config::run(&command, &options),
+        Command::Migrate(command) => {
+            commands::migrate::run(&command, &options).await
+        }
+    }
+}
+
+async fn serve() -> ExitCode {
     // Logging - The variables are needed for the lifetime of the program
     let _log_guards = utils::init_logging().await;
+    #[cfg(unix)]
+    tokio::spawn(admin::reload_on_sighup());
+
+    if ENV.config.database.degraded_start {
+        // Serve right away, requests needing the DB fail until it is up
+        tokio::spawn(async {
+            Database::connect_until_up().await;
+            event!(Level::INFO, "Connected to Postgres DB");
+            let policy = ENV.config.database.migrate_on_boot;
+            match migrations::on_boot(policy).await {
+                Ok(()) => probes::mark_migrated(),
+                Err(e) => {
+                    event!(Level::ERROR, "Failed to migrate DB! Error: {e}");
+                }
+            }
+        });
+    } else {
+        if let Err(e) = Database::try_get().await {
+            event!(
+                Level::ERROR,
+                "Failed to connect to Postgres DB! Error: {e}"
+            );
+            return ExitCode::FAILURE;
+        }
+        let policy = ENV.config.database.migrate_on_boot;
+        if let Err(e) = migrations::on_boot(policy).await {
+            event!(Level::ERROR, "Failed to migrate DB! Error: {e}");
+            Database::disconnect().await;
+            return ExitCode::FAILURE;
+        }
+        probes::mark_migrated();
+    }
+
+    repositories::spawn_sweeper(
+        PgSessionStore,
+        ENV.config.sessions.sweep_interval,
+    );
 
-    // skip migrations for faster development experience
-    if cfg!(not(debug_assertions)) {
-        // Database auto migration
-        event!(Level::INFO, "Running DB migrations...");
-        sqlx::migrate!()
-            .run(Database::get_pool().await)
-            .await
-            .unwrap_or_else(|e| panic!("Failed to migrate DB! Error: {e}"));
+    // Parse templates now rather than on the first request
+    if !views::templates_loaded() {
+        event!(Level::ERROR, "No templates found!");
+        return ExitCode::FAILURE;
     }
 
     #[cfg(debug_assertions)]
     views::setup_hotwatch();
 
-    let sock_addr = SocketAddr::from((ENV.hostname, ENV.port));
+    let sock_addr =
+        SocketAddr::from((ENV.config.server.hostname, ENV.config.server.port));
     let listener = TcpListener::bind(sock_addr)
         .await
         .unwrap_or_else(|e| panic!("Failed to bind to port! Error: {e}"));
 
     event!(Level::INFO, "Server running on http://{sock_addr}");
-    with_graceful_shutdown(axum::serve(listener, app())).await;
+    probes::mark_started();
+    serve_with_graceful_shutdown(listener, app()).await;
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,125 @@
+//! Prometheus metrics. Every crate registers its own metrics in the default
+//! registry, `/metrics` gathers them all.
+
+use std::time::Instant;
+
+use axum::extract::{MatchedPath, Request};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::{routing::get, Router};
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge_vec, Encoder, HistogramVec, IntCounter, IntCounterVec,
+    IntGaugeVec, TextEncoder,
+};
+use repositories::Database;
+use tracing::{event, Level};
+
+lazy_static! {
+    static ref REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "http_requests_total",
+        "Requests handled, by matched route and status",
+        &["method", "route", "status"]
+    )
+    .unwrap();
+    static ref REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "http_request_duration_seconds",
+        "Time to respond, by matched route",
+        &["method", "route"]
+    )
+    .unwrap();
+    static ref LOAD_SHED: IntCounter = register_int_counter!(
+        "http_load_shed_total",
+        "Requests refused with a 503 because the server was overloaded"
+    )
+    .unwrap();
+    static ref TIMEOUTS: IntCounter = register_int_counter!(
+        "http_timeouts_total",
+        "Requests aborted with a 408 after REQUEST_TIMEOUT_SECS"
+    )
+    .unwrap();
+    static ref DB_POOL_CONNECTIONS: IntGaugeVec = register_int_gauge_vec!(
+        "db_pool_connections",
+        "Connections of each database pool, by state",
+        &["pool", "state"]
+    )
+    .unwrap();
+}
+
+/// Route label of requests matching no route, so that unknown paths do not
+/// create new series.
+const UNMATCHED: &str = "<unmatched>";
+
+pub fn router() -> Router {
+    // Exported from the start, so that rates are defined before any event
+    lazy_static::initialize(&LOAD_SHED);
+    lazy_static::initialize(&TIMEOUTS);
+
+    Router::new().route("/metrics", get(metrics))
+}
+
+/// Middleware recording the RED metrics of every routed request.
+pub async fn track(request: Request, next: Next) -> Response {
+    let method = request.method().clone();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED, MatchedPath::as_str)
+        .to_owned();
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let elapsed = start.elapsed();
+
+    let status = response.status();
+    if status == StatusCode::REQUEST_TIMEOUT {
+        TIMEOUTS.inc();
+    }
+    REQUESTS
+        .with_label_values(&[method.as_str(), &route, status.as_str()])
+        .inc();
+    REQUEST_DURATION
+        .with_label_values(&[method.as_str(), &route])
+        .observe(elapsed.as_secs_f64());
+    response
+}
+
+pub fn record_load_shed() {
+    LOAD_SHED.inc();
+}
+
+async fn metrics() -> Response {
+    record_pool_stats();
+
+    let mut body = Vec::new();
+    let encoder = TextEncoder::new();
+    if let Err(e) = encoder.encode(&prometheus::gather(), &mut body) {
+        event!(Level::ERROR, "Failed to encode metrics! Error: {e}");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    ([(header::CONTENT_TYPE, encoder.format_type())], body).into_response()
+}
+
+/// Pool occupancy is read when scraped rather than tracked.
+fn record_pool_stats() {
+    for (i, stats) in Database::pool_stats().into_iter().enumerate() {
+        let pool = if i == 0 {
+            "primary".to_string()
+        } else {
+            format!("replica_{}", i - 1)
+        };
+        let idle = i64::try_from(stats.idle).unwrap_or(i64::MAX);
+        let active = i64::from(stats.size) - idle;
+        for (state, value) in [
+            ("idle", idle),
+            ("active", active),
+            ("max", i64::from(stats.max)),
+        ] {
+            DB_POOL_CONNECTIONS
+                .with_label_values(&[&pool, state])
+                .set(value);
+        }
+    }
+}
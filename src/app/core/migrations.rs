@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use anyhow::bail;
+use environment::MigrateOnBoot;
+use repositories::Database;
+use sqlx::migrate::{Migrate, MigrateError, Migration, Migrator};
+use sqlx::PgPool;
+use tracing::{event, Level};
+
+/// Migrations embedded from `src/app/migrations` at compile time.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+pub struct Status {
+    pub migration: &'static Migration,
+    pub applied: bool,
+    /// The migration was applied, but its file changed since.
+    pub changed: bool,
+}
+
+/// Compares the embedded migrations against the ones applied to the
+/// database, without writing anything to it.
+///
+/// # Errors
+/// Fails when the applied migrations cannot be listed.
+pub async fn status(pool: &PgPool) -> Result<Vec<Status>, MigrateError> {
+    let mut conn = pool.acquire().await?;
+    let table_exists: bool = sqlx::query_scalar(
+        "SELECT to_regclass('_sqlx_migrations') IS NOT NULL",
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    let applied: HashMap<_, _> = if table_exists {
+        conn.list_applied_migrations()
+            .await?
+            .into_iter()
+            .map(|m| (m.version, m.checksum))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|m| m.migration_type.is_up_migration())
+        .map(|migration| {
+            let checksum = applied.get(&migration.version);
+            Status {
+                migration,
+                applied: checksum.is_some(),
+                changed: checksum.is_some_and(|c| *c != migration.checksum),
+            }
+        })
+        .collect())
+}
+
+/// Applies the `MIGRATE_ON_BOOT` policy.
+///
+/// # Errors
+/// Fails when migrating fails, or when the policy is `check` and some
+/// migrations are pending.
+pub async fn on_boot(policy: MigrateOnBoot) -> anyhow::Result<()> {
+    match policy {
+        MigrateOnBoot::Off => {}
+        MigrateOnBoot::Auto => {
+            event!(Level::INFO, "Running DB migrations...");
+            MIGRATOR.run(Database::try_get_pool().await?).await?;
+        }
+        MigrateOnBoot::Check => {
+            let statuses = status(Database::try_get_pool().await?).await?;
+            let pending = statuses.iter().filter(|s| !s.applied).count();
+            if pending > 0 {
+                bail!(
+                    "{pending} pending migration(s). Run `app migrate up`, \
+                     or set MIGRATE_ON_BOOT=auto"
+                );
+            }
+        }
+    }
+    Ok(())
+}
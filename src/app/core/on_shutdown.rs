@@ -1,28 +1,155 @@
-use axum::{serve::Serve, Router};
-use repositories::Database;
-use tokio::signal;
+use std::future::Future;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use axum::Router;
+use environment::ENV;
+use hyper_util::rt::TokioIo;
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::server::graceful::GracefulShutdown;
+use hyper_util::service::TowerToHyperService;
+use tokio::net::TcpListener;
+use tokio::time::{self, Instant};
+use tokio::{select, signal};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
 use tracing::{event, Level};
 
-fn before_axum() {
+/// Set as soon as a shutdown signal is received, before draining.
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+pub fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::Relaxed)
+}
+
+/// Reports not ready right away, and keeps accepting connections for the
+/// pre-drain delay, while load balancers take this instance out.
+async fn pre_drain() {
+    SHUTTING_DOWN.store(true, Ordering::Relaxed);
     event!(Level::WARN, "The server is shutting down!");
-    event!(Level::INFO, "Waiting for pending requests (max. 15s)...");
+
+    let delay = ENV.config.server.shutdown_pre_drain;
+    if !delay.is_zero() {
+        event!(Level::INFO, "Not ready anymore, draining in {delay:?}...");
+        time::sleep(delay).await;
+    }
 }
 
-async fn after_axum() {
-    event!(Level::DEBUG, "All pending requests have been processed!");
-    Database::disconnect().await;
+/// Every task serving a connection, HTTP/2 streams included, so that they can
+/// be waited for, and aborted once past the shutdown budget.
+#[derive(Clone)]
+struct Connections {
+    tasks: TaskTracker,
+    abort: CancellationToken,
 }
 
-pub async fn with_graceful_shutdown(axum_serve: Serve<Router, Router>) {
-    drop(
-        axum_serve
-            .with_graceful_shutdown(async {
-                shutdown_signal().await;
-                before_axum();
-            })
-            .await,
+impl<F> hyper::rt::Executor<F> for Connections
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    fn execute(&self, future: F) {
+        let abort = self.abort.clone();
+        self.tasks.spawn(async move {
+            select! {
+                _ = future => {}
+                () = abort.cancelled() => {}
+            }
+        });
+    }
+}
+
+/// Serves `app` until a shutdown signal, then gives pending requests the
+/// shutdown budget, less what is reserved for the shutdown hooks. Requests
+/// still running past it are aborted. A second signal quits right away.
+pub async fn serve_with_graceful_shutdown(listener: TcpListener, app: Router) {
+    let connections = Connections {
+        tasks: TaskTracker::new(),
+        abort: CancellationToken::new(),
+    };
+    let graceful = GracefulShutdown::new();
+    let stop = async {
+        shutdown_signal().await;
+        tokio::spawn(force_quit_on_second_signal());
+        pre_drain().await;
+    };
+    tokio::pin!(stop);
+
+    loop {
+        let stream = select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    on_accept_error(&e).await;
+                    continue;
+                }
+            },
+            () = &mut stop => break,
+        };
+        let service = TowerToHyperService::new(app.clone());
+        let connection = Builder::new(connections.clone())
+            .serve_connection_with_upgrades(TokioIo::new(stream), service)
+            .into_owned();
+        let connection = graceful.watch(connection);
+        hyper::rt::Executor::execute(&connections, async move {
+            if let Err(e) = connection.await {
+                event!(Level::TRACE, "Failed to serve connection! Error: {e}");
+            }
+        });
+    }
+    drop(listener);
+
+    let budget = ENV.config.server.shutdown_timeout;
+    let drain_budget = budget.saturating_sub(ENV.config.server.shutdown_hooks);
+    let deadline = Instant::now() + budget;
+    event!(
+        Level::INFO,
+        "Waiting for pending requests (max. {drain_budget:?})..."
+    );
+    connections.tasks.close();
+    let drain = async {
+        graceful.shutdown().await;
+        connections.tasks.wait().await;
+    };
+    if time::timeout(drain_budget, drain).await.is_ok() {
+        event!(Level::DEBUG, "All pending requests were processed!");
+    } else {
+        event!(
+            Level::WARN,
+            "Aborting {} connections pending past the shutdown budget!",
+            connections.tasks.len()
+        );
+        connections.abort.cancel();
+        connections.tasks.wait().await;
+    }
+
+    // Whatever is left, at least what was reserved for the hooks
+    utils::run_shutdown_hooks(deadline).await;
+}
+
+/// Connection errors only concern a client. Others, e.g. running out of file
+/// descriptors, are retried after a while, hoping some get closed meanwhile.
+async fn on_accept_error(e: &io::Error) {
+    if matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+    ) {
+        return;
+    }
+    event!(Level::ERROR, "Failed to accept a connection! Error: {e}");
+    time::sleep(Duration::from_secs(1)).await;
+}
+
+async fn force_quit_on_second_signal() {
+    shutdown_signal().await;
+    event!(
+        Level::ERROR,
+        "Second shutdown signal received, quitting now!"
     );
-    after_axum().await;
+    std::process::exit(1);
 }
 
 /// # Panics
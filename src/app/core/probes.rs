@@ -0,0 +1,102 @@
+//! Kubernetes probes. They are mounted outside of every other layer, so that
+//! they answer under load and do not flood the traces.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use axum::http::StatusCode;
+use axum::response::Json;
+use axum::{routing::get, Router};
+use custom_errors::err_response::{res, JsonResult};
+use environment::{MigrateOnBoot, ENV};
+use repositories::{Database, Health};
+use serde::Serialize;
+use types::api;
+
+use crate::{migrations, on_shutdown};
+
+/// The server is bound and the boot sequence is over.
+static STARTED: AtomicBool = AtomicBool::new(false);
+/// `MIGRATE_ON_BOOT` was applied successfully. Not used when it is `off`.
+static MIGRATED: AtomicBool = AtomicBool::new(false);
+
+pub fn mark_started() {
+    STARTED.store(true, Ordering::Relaxed);
+}
+
+pub fn mark_migrated() {
+    MIGRATED.store(true, Ordering::Relaxed);
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/startupz", get(startupz))
+}
+
+#[derive(Serialize)]
+struct Readiness {
+    database: Health,
+    migrated: bool,
+    templates_loaded: bool,
+    shutting_down: bool,
+}
+
+impl Readiness {
+    /// Names of the failing checks.
+    fn failing(&self) -> Vec<&'static str> {
+        [
+            (!self.database.reachable, "database"),
+            (!self.migrated, "migrations"),
+            (!self.templates_loaded, "templates"),
+            (self.shutting_down, "shutting down"),
+        ]
+        .into_iter()
+        .filter_map(|(failing, name)| failing.then_some(name))
+        .collect()
+    }
+}
+
+/// The process is alive.
+async fn healthz() -> JsonResult {
+    res(Json(api::Response::success("alive")))
+}
+
+/// The instance can take traffic.
+async fn readyz() -> JsonResult {
+    let readiness = Readiness {
+        database: Database::health().await,
+        migrated: is_migrated().await,
+        templates_loaded: views::templates_loaded(),
+        shutting_down: on_shutdown::is_shutting_down(),
+    };
+    let failing = readiness.failing();
+    if failing.is_empty() {
+        return res(Json(api::Response::success(readiness)));
+    }
+    let message = format!("Not ready: {}", failing.join(", "));
+    let response = api::Response::error_with(message, readiness);
+    res((StatusCode::SERVICE_UNAVAILABLE, Json(response)))
+}
+
+/// Whether every embedded migration was applied. Without a migration policy
+/// at boot, the database is asked, as migrations are run by someone else.
+async fn is_migrated() -> bool {
+    if ENV.config.database.migrate_on_boot != MigrateOnBoot::Off {
+        return MIGRATED.load(Ordering::Relaxed);
+    }
+    let Ok(pool) = Database::try_get_pool().await else {
+        return false;
+    };
+    let statuses = migrations::status(pool).await;
+    statuses.is_ok_and(|statuses| statuses.iter().all(|s| s.applied))
+}
+
+/// The boot sequence is over.
+async fn startupz() -> JsonResult {
+    if STARTED.load(Ordering::Relaxed) {
+        return res(Json(api::Response::success("started")));
+    }
+    let response = api::Response::<()>::error("Starting".to_string(), None);
+    res((StatusCode::SERVICE_UNAVAILABLE, Json(response)))
+}
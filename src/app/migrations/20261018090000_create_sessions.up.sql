@@ -0,0 +1,9 @@
+CREATE TABLE sessions (
+    id TEXT PRIMARY KEY,
+    data JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+-- Used by the expiry sweeper
+CREATE INDEX sessions_expires_at_idx ON sessions (expires_at);
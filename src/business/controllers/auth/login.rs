@@ -0,0 +1,50 @@
+use axum::extract::Query;
+use axum::http::StatusCode;
+use axum::response::{Html, Redirect};
+use axum::Form;
+use axum_extra::extract::WithRejection;
+use custom_errors::app_rejection::WithHtmlRejection;
+use custom_errors::err_response::{res, HtmlResult};
+use serde::Deserialize;
+use tracing::{event, Level};
+use views::login::render;
+
+use crate::extractors::{is_local_path, sign_in, Session, Tx};
+
+#[derive(Deserialize)]
+pub struct LoginQuery {
+    next: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LoginForm {
+    email: String,
+    password: String,
+    next: Option<String>,
+}
+
+pub async fn get(
+    WithRejection(Query(query), _): WithHtmlRejection<Query<LoginQuery>>,
+) -> HtmlResult {
+    let next = query.next.as_deref().filter(|next| is_local_path(next));
+    res(Html(render("", None, next)?))
+}
+
+pub async fn post(
+    session: Session,
+    mut tx: Tx,
+    WithRejection(Form(form), _): WithHtmlRejection<Form<LoginForm>>,
+) -> HtmlResult {
+    let next = form.next.as_deref().filter(|next| is_local_path(next));
+    let conn = tx.conn().await?;
+    let user = services::authenticate(conn, &form.email, form.password).await?;
+    let Some(user) = user else {
+        let error = Some("Invalid email or password");
+        let page = render(&form.email, error, next)?;
+        return res((StatusCode::UNAUTHORIZED, Html(page)));
+    };
+
+    sign_in(&session, &user)?;
+    event!(Level::INFO, "User {} logged in", user.id);
+    res(Redirect::to(next.unwrap_or("/")))
+}
@@ -0,0 +1,9 @@
+use axum::response::Redirect;
+use custom_errors::err_response::{res, HtmlResult};
+
+use crate::extractors::{sign_out, Session};
+
+pub async fn post(session: Session) -> HtmlResult {
+    sign_out(&session);
+    res(Redirect::to("/"))
+}
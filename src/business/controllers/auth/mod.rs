@@ -0,0 +1,16 @@
+mod login;
+mod logout;
+mod register;
+mod tokens;
+
+use axum::routing::{delete, get, post};
+use axum::Router;
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/register", get(register::get).post(register::post))
+        .route("/login", get(login::get).post(login::post))
+        .route("/logout", post(logout::post))
+        .route("/tokens", get(tokens::list).post(tokens::create))
+        .route("/tokens/:id", delete(tokens::revoke))
+}
@@ -0,0 +1,59 @@
+use axum::http::StatusCode;
+use axum::response::{Html, Redirect};
+use axum::Form;
+use axum_extra::extract::WithRejection;
+use custom_errors::app_rejection::WithHtmlRejection;
+use custom_errors::err_response::{res, HtmlResult};
+use serde::Deserialize;
+use tracing::{event, Level};
+use validator::Validate;
+use views::register::render;
+
+use crate::extractors::{sign_in, Session, Tx};
+
+#[derive(Validate, Deserialize)]
+pub struct RegisterForm {
+    #[validate(email(message = "Enter a valid email address"))]
+    email: String,
+    #[validate(length(
+        min = 8,
+        max = 1024,
+        message = "Passwords are 8 to 1024 characters long"
+    ))]
+    password: String,
+}
+
+pub async fn get() -> HtmlResult {
+    res(Html(render("", Vec::new())?))
+}
+
+pub async fn post(
+    session: Session,
+    mut tx: Tx,
+    WithRejection(Form(mut form), _): WithHtmlRejection<Form<RegisterForm>>,
+) -> HtmlResult {
+    form.email = services::normalize_email(&form.email);
+    if let Err(e) = form.validate() {
+        let errors = e
+            .field_errors()
+            .values()
+            .flat_map(|errors| errors.iter())
+            .filter_map(|error| error.message.as_ref())
+            .map(ToString::to_string)
+            .collect();
+        let page = render(&form.email, errors)?;
+        return res((StatusCode::UNPROCESSABLE_ENTITY, Html(page)));
+    }
+
+    let conn = tx.conn().await?;
+    let user = services::register(conn, &form.email, form.password).await?;
+    let Some(user) = user else {
+        let errors = vec!["This email is already registered".to_string()];
+        let page = render(&form.email, errors)?;
+        return res((StatusCode::CONFLICT, Html(page)));
+    };
+
+    sign_in(&session, &user)?;
+    event!(Level::INFO, "User {} registered", user.id);
+    res(Redirect::to("/"))
+}
@@ -0,0 +1,142 @@
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::response::Json;
+use axum::Json as JsonExt;
+use axum_extra::extract::WithRejection;
+use chrono::{DateTime, TimeDelta, Utc};
+use custom_errors::app_rejection::WithJsonRejection;
+use custom_errors::err_response::{res, ErrResponse, JsonResult};
+use serde::{Deserialize, Serialize};
+use services::ScopeError;
+use tracing::{event, Level};
+use types::api;
+use types::entities::ApiToken;
+use validator::Validate;
+
+use crate::extractors::{AuthUser, Tx};
+
+/// A token as listed, without its hash.
+#[derive(Serialize)]
+pub struct TokenInfo {
+    id: i64,
+    name: String,
+    scopes: Vec<String>,
+    created_at: DateTime<Utc>,
+    last_used_at: Option<DateTime<Utc>>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<ApiToken> for TokenInfo {
+    fn from(token: ApiToken) -> Self {
+        Self {
+            id: token.id,
+            name: token.name,
+            scopes: token.scopes,
+            created_at: token.created_at,
+            last_used_at: token.last_used_at,
+            expires_at: token.expires_at,
+        }
+    }
+}
+
+/// The only time the token itself is returned.
+#[derive(Serialize)]
+pub struct CreatedToken {
+    token: String,
+    #[serde(flatten)]
+    info: TokenInfo,
+}
+
+#[derive(Validate, Deserialize)]
+pub struct NewToken {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "name is 1 to 100 characters!"
+    ))]
+    name: String,
+    /// Permissions of the user, see `services::create_token`.
+    #[serde(default)]
+    #[validate(length(max = 32, message = "scopes has at most 32 scopes!"))]
+    scopes: Vec<String>,
+    /// Never expires when missing.
+    #[validate(range(
+        min = 1,
+        max = 3650,
+        message = "expires_in_days is 1 to 3650!"
+    ))]
+    expires_in_days: Option<i64>,
+}
+
+pub async fn list(AuthUser(user): AuthUser, mut tx: Tx) -> JsonResult {
+    let tokens = services::list_tokens(tx.conn().await?, user.id).await?;
+    let tokens: Vec<TokenInfo> = tokens.into_iter().map(Into::into).collect();
+    res(Json(api::Response::success(tokens)))
+}
+
+pub async fn create(
+    AuthUser(user): AuthUser,
+    mut tx: Tx,
+    WithRejection(JsonExt(json), _): WithJsonRejection<JsonExt<NewToken>>,
+) -> JsonResult {
+    if let Err(e) = json.validate() {
+        let errors = e
+            .field_errors()
+            .iter()
+            .flat_map(|e| e.1.iter())
+            .filter_map(|e| e.message.as_ref())
+            .fold(String::new(), |acc, e| acc + e + "\n");
+        let message = format!("Whoops, validation errors! {errors}");
+        let status = StatusCode::UNPROCESSABLE_ENTITY;
+        return Err(ErrResponse::new(message, status, None));
+    }
+
+    let expires_at = json
+        .expires_in_days
+        .map(|days| Utc::now() + TimeDelta::days(days));
+    let conn = tx.conn().await?;
+    let created = services::create_token(
+        conn,
+        user.id,
+        json.name,
+        json.scopes,
+        expires_at,
+    )
+    .await?;
+    let created = match created {
+        Ok(created) => created,
+        Err(e) => {
+            let status = match e {
+                ScopeError::TooLong => StatusCode::UNPROCESSABLE_ENTITY,
+                ScopeError::NotGranted(_) => StatusCode::FORBIDDEN,
+            };
+            return Err(ErrResponse::new(e.to_string(), status, None));
+        }
+    };
+    event!(
+        Level::INFO,
+        "User {} created API token {}",
+        user.id,
+        created.record.id
+    );
+
+    let response = api::Response::success(CreatedToken {
+        token: created.token,
+        info: created.record.into(),
+    });
+    res((StatusCode::CREATED, Json(response)))
+}
+
+pub async fn revoke(
+    AuthUser(user): AuthUser,
+    mut tx: Tx,
+    WithRejection(Path(id), _): WithJsonRejection<Path<i64>>,
+) -> JsonResult {
+    let conn = tx.conn().await?;
+    if !services::revoke_token(conn, user.id, id).await? {
+        let message = "No such API token".to_string();
+        return Err(ErrResponse::new(message, StatusCode::NOT_FOUND, None));
+    }
+    event!(Level::INFO, "User {} revoked API token {id}", user.id);
+    res(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,194 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::{header, request::Parts, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use custom_errors::err_response::{ErrResponse, JsonKind};
+use repositories::Database;
+use types::entities::{ApiToken, User};
+
+use super::{load_permissions, Permission, UserPermissions};
+
+/// The user of the `Authorization: Bearer` API token, or a JSON 401.
+#[derive(Clone)]
+pub struct ApiUser {
+    pub user: User,
+    pub token: ApiToken,
+    pub permissions: UserPermissions,
+}
+
+impl ApiUser {
+    /// Whether the token has scope `P`, and the user still has permission `P`.
+    #[must_use]
+    pub fn has_scope<P: Permission>(&self) -> bool {
+        self.token.scopes.iter().any(|scope| scope == P::NAME)
+            && self.permissions.has::<P>()
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ApiUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        if let Some(api_user) = parts.extensions.get::<Self>() {
+            return Ok(api_user.clone());
+        }
+        let Some(token) = bearer(&parts.headers) else {
+            return Err(unauthorized("Missing API token"));
+        };
+        let authenticated = authenticate(token)
+            .await
+            .map_err(|e| ErrResponse::<JsonKind>::from(e).into_response())?;
+        let Some(api_user) = authenticated else {
+            return Err(unauthorized("Invalid or expired API token"));
+        };
+        parts.extensions.insert(api_user.clone());
+        Ok(api_user)
+    }
+}
+
+async fn authenticate(token: &str) -> anyhow::Result<Option<ApiUser>> {
+    // The primary, like `load_permissions`
+    let pool = Database::try_get_pool().await?;
+    let Some((user, token)) = services::authenticate_token(pool, token).await?
+    else {
+        return Ok(None);
+    };
+    let permissions = load_permissions(user.id).await?;
+    Ok(Some(ApiUser {
+        user,
+        token,
+        permissions: UserPermissions(Arc::new(permissions)),
+    }))
+}
+
+/// Like `RequirePermission`, for the scope `P` of an `ApiUser`.
+pub struct RequireScope<P>(pub ApiUser, pub PhantomData<P>);
+
+#[async_trait]
+impl<S: Send + Sync, P: Permission> FromRequestParts<S> for RequireScope<P> {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let api_user = ApiUser::from_request_parts(parts, state).await?;
+        if !api_user.has_scope::<P>() {
+            let message = format!("The API token lacks the {} scope", P::NAME);
+            let challenge = format!(
+                "Bearer error=\"insufficient_scope\", scope=\"{}\"",
+                P::NAME
+            );
+            let error = ErrResponse::<JsonKind>::new(
+                message,
+                StatusCode::FORBIDDEN,
+                None,
+            );
+            let headers = [(header::WWW_AUTHENTICATE, challenge)];
+            return Err((headers, error).into_response());
+        }
+        Ok(Self(api_user, PhantomData))
+    }
+}
+
+/// The token of an `Authorization: Bearer <token>` header. The scheme is
+/// case-insensitive, as per RFC 7235.
+#[must_use]
+pub fn bearer(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    let token = token.trim();
+    (scheme.eq_ignore_ascii_case("Bearer") && !token.is_empty())
+        .then_some(token)
+}
+
+fn unauthorized(message: &str) -> Response {
+    let error = ErrResponse::<JsonKind>::new(
+        message.to_string(),
+        StatusCode::UNAUTHORIZED,
+        None,
+    );
+    ([(header::WWW_AUTHENTICATE, "Bearer")], error).into_response()
+}
+
+#[tokio::test]
+async fn test() {
+    use axum::body::Body;
+    use axum::extract::Request;
+    use axum::routing::get;
+    use axum::Router;
+    use chrono::Utc;
+    use tower::ServiceExt;
+
+    struct Read;
+    impl Permission for Read {
+        const NAME: &'static str = "things.read";
+    }
+
+    let mut headers = HeaderMap::new();
+    assert_eq!(bearer(&headers), None);
+    headers.insert(header::AUTHORIZATION, "Basic YTpi".parse().unwrap());
+    assert_eq!(bearer(&headers), None);
+    headers.insert(header::AUTHORIZATION, "Bearer pat_x".parse().unwrap());
+    assert_eq!(bearer(&headers), Some("pat_x"));
+    headers.insert(header::AUTHORIZATION, "bearer pat_x".parse().unwrap());
+    assert_eq!(bearer(&headers), Some("pat_x"));
+    headers.insert(header::AUTHORIZATION, "BEARER  pat_x".parse().unwrap());
+    assert_eq!(bearer(&headers), Some("pat_x"));
+    headers.insert(header::AUTHORIZATION, "Bearer ".parse().unwrap());
+    assert_eq!(bearer(&headers), None);
+    headers.insert(header::AUTHORIZATION, "Bearerpat_x".parse().unwrap());
+    assert_eq!(bearer(&headers), None);
+
+    let app =
+        Router::new().route("/", get(|_: RequireScope<Read>| async { "ok" }));
+    let respond = |api_user: Option<ApiUser>| {
+        let mut request =
+            Request::builder().uri("/").body(Body::empty()).unwrap();
+        if let Some(api_user) = api_user {
+            // Cached as if authenticated, so that no database is needed
+            request.extensions_mut().insert(api_user);
+        }
+        app.clone().oneshot(request)
+    };
+    let api_user = |scopes: &[&str], granted: &[&str]| ApiUser {
+        user: User {
+            id: 1,
+            email: "ada@example.com".to_string(),
+            password_hash: String::new(),
+            created_at: Utc::now(),
+        },
+        token: ApiToken {
+            id: 1,
+            user_id: 1,
+            name: "ci".to_string(),
+            token_hash: Vec::new(),
+            scopes: scopes.iter().map(ToString::to_string).collect(),
+            created_at: Utc::now(),
+            last_used_at: None,
+            expires_at: None,
+        },
+        permissions: UserPermissions(Arc::new(
+            granted.iter().map(ToString::to_string).collect(),
+        )),
+    };
+
+    let response = respond(None).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+    let read = &["things.read"];
+    let response = respond(Some(api_user(&["things.write"], read))).await;
+    assert_eq!(response.unwrap().status(), StatusCode::FORBIDDEN);
+    // The user lost the permission since the token was created
+    let response = respond(Some(api_user(read, &[]))).await;
+    assert_eq!(response.unwrap().status(), StatusCode::FORBIDDEN);
+    let response = respond(Some(api_user(read, read))).await;
+    assert_eq!(response.unwrap().status(), StatusCode::OK);
+}
@@ -0,0 +1,129 @@
+use axum::async_trait;
+use axum::extract::{FromRequestParts, OriginalUri};
+use axum::http::{request::Parts, StatusCode};
+use axum::response::{IntoResponse, Redirect, Response};
+use repositories::{Database, Loadable, Repository};
+use types::entities::User;
+
+use super::{fail, reject, wants_json, Session};
+
+/// Session key of the id of the signed in user.
+const USER_ID: &str = "user_id";
+
+/// Where `AuthUser` sends HTML requests without a user.
+pub const LOGIN_PATH: &str = "/auth/login";
+
+/// Signs `user` in, under a new session id.
+///
+/// # Errors
+///
+/// Fails when the id cannot be serialized, which it always can.
+pub fn sign_in(session: &Session, user: &User) -> serde_json::Result<()> {
+    session.insert(USER_ID, &user.id)?;
+    session.rotate_id();
+    Ok(())
+}
+
+/// Signs the user out, deleting the whole session.
+pub fn sign_out(session: &Session) {
+    session.destroy();
+}
+
+/// The signed in user. Without one, HTML requests are redirected to the
+/// login page, and JSON requests get a 401.
+///
+/// Needs the `sessions` middleware.
+pub struct AuthUser(pub User);
+
+/// The signed in user, if any. Loaded once per request, however many
+/// extractors ask for it.
+///
+/// Needs the `sessions` middleware.
+#[derive(Clone)]
+pub struct MaybeUser(pub Option<User>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for MaybeUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        if let Some(user) = parts.extensions.get::<Self>() {
+            return Ok(user.clone());
+        }
+        let session = Session::from_request_parts(parts, state).await?;
+        let user = load_user(&session)
+            .await
+            .map(Self)
+            .map_err(|e| fail(&parts.headers, e))?;
+        parts.extensions.insert(user.clone());
+        Ok(user)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let MaybeUser(user) =
+            MaybeUser::from_request_parts(parts, state).await?;
+        user.map(Self).ok_or_else(|| unauthenticated(parts))
+    }
+}
+
+async fn load_user(session: &Session) -> Loadable<User> {
+    let Some(id) = session.get::<i64>(USER_ID)? else {
+        return Ok(None);
+    };
+    // The primary, as a replica may not have the user yet
+    let pool = Database::try_get_pool().await?;
+    pool.find_by_id(&id).await
+}
+
+fn unauthenticated(parts: &Parts) -> Response {
+    if wants_json(&parts.headers) {
+        let status = StatusCode::UNAUTHORIZED;
+        return reject(&parts.headers, "Authentication required", status);
+    }
+    // The URI of nested routers lacks their prefix
+    let uri = parts
+        .extensions
+        .get::<OriginalUri>()
+        .map_or(&parts.uri, |original| &original.0);
+    let next = uri.path_and_query().map_or("/", |pq| pq.as_str());
+    Redirect::to(&login_url(next)).into_response()
+}
+
+/// The login page, coming back to `next` once logged in.
+fn login_url(next: &str) -> String {
+    let query = serde_urlencoded::to_string([("next", next)]);
+    query.map_or_else(
+        |_| LOGIN_PATH.to_string(),
+        |q| format!("{LOGIN_PATH}?{q}"),
+    )
+}
+
+/// Whether `next` stays on this site, so that the login page cannot be
+/// used to redirect elsewhere.
+#[must_use]
+pub fn is_local_path(next: &str) -> bool {
+    next.starts_with('/') && !next.starts_with("//") && !next.contains('\\')
+}
+
+#[test]
+fn test() {
+    assert_eq!(
+        login_url("/account?tab=keys&x=1"),
+        "/auth/login?next=%2Faccount%3Ftab%3Dkeys%26x%3D1"
+    );
+    assert!(is_local_path("/account"));
+    assert!(!is_local_path("//evil.example"));
+    assert!(!is_local_path("/\\evil.example"));
+    assert!(!is_local_path("https://evil.example"));
+}
@@ -0,0 +1,239 @@
+use std::borrow::Cow;
+use std::convert::Infallible;
+
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::response::{
+    IntoResponse, IntoResponseParts, Response, ResponseParts,
+};
+use axum_extra::extract::cookie::{
+    Cookie, Key, PrivateCookieJar, SameSite, SignedCookieJar,
+};
+use cookie::CookieBuilder;
+use environment::{CookieSameSite, ENV};
+use lazy_static::lazy_static;
+use tracing::{event, Level};
+
+lazy_static! {
+    static ref POLICY: CookiePolicy = CookiePolicy::from_env();
+    static ref KEYS: Keys = Keys::from_env();
+}
+
+/// The attributes every cookie is set with, from the `cookies` settings.
+#[derive(Debug, Clone)]
+pub struct CookiePolicy {
+    pub domain: String,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: SameSite,
+}
+
+impl CookiePolicy {
+    #[must_use]
+    pub fn from_env() -> Self {
+        let config = &ENV.config.cookies;
+        Self {
+            domain: config.domain.clone(),
+            secure: config.secure,
+            http_only: config.http_only,
+            same_site: match config.same_site {
+                CookieSameSite::Strict => SameSite::Strict,
+                CookieSameSite::Lax => SameSite::Lax,
+                CookieSameSite::None => SameSite::None,
+            },
+        }
+    }
+
+    /// A cookie for the whole site, following the policy. Attributes it does
+    /// not set (e.g. `max_age`) can be added to the builder.
+    pub fn build<N, V>(&self, name: N, value: V) -> CookieBuilder<'static>
+    where
+        N: Into<Cow<'static, str>>,
+        V: Into<Cow<'static, str>>,
+    {
+        Cookie::build((name, value))
+            .domain(self.domain.clone())
+            .path("/")
+            .secure(self.secure)
+            .http_only(self.http_only)
+            .same_site(self.same_site)
+    }
+
+    /// A cookie deleting the one named `name`.
+    pub fn removal<N>(&self, name: N) -> Cookie<'static>
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        let mut cookie = self.build(name, "").build();
+        cookie.make_removal();
+        cookie
+    }
+}
+
+/// A cookie following the configured `CookiePolicy`, see
+/// `CookiePolicy::build`.
+pub fn cookie<N, V>(name: N, value: V) -> CookieBuilder<'static>
+where
+    N: Into<Cow<'static, str>>,
+    V: Into<Cow<'static, str>>,
+{
+    POLICY.build(name, value)
+}
+
+/// Keys derived from the master key, and from the previous ones.
+struct Keys {
+    current: Key,
+    previous: Vec<Key>,
+}
+
+impl Keys {
+    fn from_env() -> Self {
+        let config = &ENV.config.cookies;
+        let current = config.master_key.as_ref().map_or_else(
+            || {
+                event!(
+                    Level::WARN,
+                    "COOKIE_MASTER_KEY is not set, signed and private \
+                     cookies will not survive a restart!"
+                );
+                Key::generate()
+            },
+            |master_key| Key::derive_from(master_key),
+        );
+        Self {
+            current,
+            previous: config
+                .previous_keys
+                .iter()
+                .map(|master_key| Key::derive_from(master_key))
+                .collect(),
+        }
+    }
+}
+
+/// Both jars only differ by how their cookies are protected.
+macro_rules! rotating_jar {
+    ($(#[$doc:meta])* $name:ident, $jar:ident) => {
+        $(#[$doc])*
+        ///
+        /// Cookies protected with a previous key (see `COOKIE_PREVIOUS_KEYS`)
+        /// are still read, and protected with the current key once set again.
+        pub struct $name {
+            jar: $jar,
+            /// The request cookies, as read with each previous key.
+            previous: Vec<$jar>,
+        }
+
+        impl $name {
+            /// The value of the cookie named `name`, if it was protected with
+            /// the current or a previous key.
+            #[must_use]
+            pub fn get(&self, name: &str) -> Option<Cookie<'static>> {
+                self.jar.get(name).or_else(|| {
+                    self.previous.iter().find_map(|jar| jar.get(name))
+                })
+            }
+
+            /// Sets `cookie`, usually built with `cookie`.
+            #[must_use]
+            #[allow(clippy::should_implement_trait)] // As in `axum_extra`
+            pub fn add<C: Into<Cookie<'static>>>(mut self, cookie: C) -> Self {
+                self.jar = self.jar.add(cookie);
+                self
+            }
+
+            /// Deletes the cookie named `name`, if the request had it.
+            #[must_use]
+            pub fn remove(mut self, name: &str) -> Self {
+                // Added rather than removed, as the current jar does not
+                // know cookies protected with a previous key
+                if self.get(name).is_some() {
+                    self.jar = self.jar.add(POLICY.removal(name.to_string()));
+                }
+                self
+            }
+        }
+
+        #[async_trait]
+        impl<S: Send + Sync> FromRequestParts<S> for $name {
+            type Rejection = Infallible;
+
+            async fn from_request_parts(
+                parts: &mut Parts,
+                _state: &S,
+            ) -> Result<Self, Self::Rejection> {
+                let headers = &parts.headers;
+                Ok(Self {
+                    jar: $jar::from_headers(headers, KEYS.current.clone()),
+                    previous: KEYS
+                        .previous
+                        .iter()
+                        .map(|key| $jar::from_headers(headers, key.clone()))
+                        .collect(),
+                })
+            }
+        }
+
+        impl IntoResponseParts for $name {
+            type Error = Infallible;
+
+            fn into_response_parts(
+                self,
+                res: ResponseParts,
+            ) -> Result<ResponseParts, Self::Error> {
+                self.jar.into_response_parts(res)
+            }
+        }
+
+        impl IntoResponse for $name {
+            fn into_response(self) -> Response {
+                self.jar.into_response()
+            }
+        }
+    };
+}
+
+rotating_jar!(
+    /// Cookies signed with the master key: the client can read them, but
+    /// not change them.
+    SignedCookies,
+    SignedCookieJar
+);
+
+rotating_jar!(
+    /// Cookies encrypted with the master key: the client can neither read
+    /// nor change them.
+    PrivateCookies,
+    PrivateCookieJar
+);
+
+#[test]
+fn test() {
+    use axum::http::{header, HeaderMap};
+
+    let old = Key::generate();
+    let current = Key::generate();
+    let set_by = |jar: SignedCookieJar| {
+        let response = jar.add(Cookie::new("theme", "dark")).into_response();
+        let set_cookie = response.headers()[header::SET_COOKIE].clone();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, set_cookie);
+        headers
+    };
+
+    // Signed with the previous key, still read after rotating
+    let headers = set_by(SignedCookieJar::new(old.clone()));
+    let cookies = SignedCookies {
+        jar: SignedCookieJar::from_headers(&headers, current.clone()),
+        previous: vec![SignedCookieJar::from_headers(&headers, old)],
+    };
+    assert_eq!(cookies.get("theme").unwrap().value(), "dark");
+
+    // Signed with an unknown key, or tampered with
+    let cookies = SignedCookies {
+        jar: SignedCookieJar::from_headers(&headers, current),
+        previous: Vec::new(),
+    };
+    assert!(cookies.get("theme").is_none());
+}
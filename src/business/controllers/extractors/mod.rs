@@ -0,0 +1,88 @@
+use std::collections::HashSet;
+
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use custom_errors::err_response::{ErrResponse, HtmlKind, JsonKind};
+use repositories::Database;
+
+mod tx;
+pub use tx::*;
+
+mod cookies;
+pub use cookies::*;
+
+mod session;
+pub use session::*;
+
+mod auth_user;
+pub use auth_user::*;
+
+mod permission;
+pub use permission::*;
+
+mod api_user;
+pub use api_user::*;
+
+/// Whether the changes a request made are kept: the handler succeeded or
+/// redirected, and did not return an `ErrResponse`.
+fn should_commit(status: StatusCode, failed: bool) -> bool {
+    !failed && (status.is_success() || status.is_redirection())
+}
+
+fn is_json(response: &Response) -> bool {
+    is_json_type(response.headers(), header::CONTENT_TYPE)
+}
+
+/// Whether the client expects JSON rather than a page, for errors raised
+/// before there is a response.
+fn wants_json(headers: &HeaderMap) -> bool {
+    is_json_type(headers, header::ACCEPT)
+        || is_json_type(headers, header::CONTENT_TYPE)
+}
+
+/// An error of the kind the client expects, see `wants_json`.
+fn reject(headers: &HeaderMap, message: &str, status: StatusCode) -> Response {
+    let message = message.to_string();
+    if wants_json(headers) {
+        ErrResponse::<JsonKind>::new(message, status, None).into_response()
+    } else {
+        ErrResponse::<HtmlKind>::new(message, status, None).into_response()
+    }
+}
+
+/// A 500 of the kind the client expects, see `wants_json`.
+fn fail(headers: &HeaderMap, e: anyhow::Error) -> Response {
+    if wants_json(headers) {
+        ErrResponse::<JsonKind>::from(e).into_response()
+    } else {
+        ErrResponse::<HtmlKind>::from(e).into_response()
+    }
+}
+
+/// Names of the permissions of a user. Read from the primary, so that
+/// revoking a role applies right away.
+async fn load_permissions(user_id: i64) -> anyhow::Result<HashSet<String>> {
+    let pool = Database::try_get_pool().await?;
+    services::permissions_of(pool, user_id).await
+}
+
+fn is_json_type(headers: &HeaderMap, name: header::HeaderName) -> bool {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("application/json"))
+}
+
+#[test]
+fn test() {
+    assert!(should_commit(StatusCode::OK, false));
+    assert!(should_commit(StatusCode::SEE_OTHER, false));
+    assert!(!should_commit(StatusCode::OK, true));
+    assert!(!should_commit(StatusCode::NOT_FOUND, false));
+    assert!(!should_commit(StatusCode::INTERNAL_SERVER_ERROR, false));
+
+    let mut headers = HeaderMap::new();
+    assert!(!wants_json(&headers));
+    headers.insert(header::ACCEPT, "application/json".parse().unwrap());
+    assert!(wants_json(&headers));
+}
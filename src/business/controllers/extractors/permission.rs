@@ -0,0 +1,170 @@
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use axum::async_trait;
+use axum::extract::{FromRequestParts, Request};
+use axum::http::{request::Parts, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::Router;
+use tracing::{event, Level};
+use types::entities::User;
+
+use super::{fail, load_permissions, reject, AuthUser, MaybeUser};
+
+/// A permission, named as in the `permissions` table, e.g.
+///
+/// ```
+/// # use controllers::extractors::Permission;
+/// pub struct ManageUsers;
+/// impl Permission for ManageUsers {
+///     const NAME: &'static str = "users.manage";
+/// }
+/// ```
+pub trait Permission: Send + Sync + 'static {
+    const NAME: &'static str;
+}
+
+/// Names of the permissions of the signed in user, through all of their
+/// roles. Empty without a user. Loaded once per request.
+///
+/// Needs the `sessions` middleware.
+#[derive(Debug, Clone, Default)]
+pub struct UserPermissions(pub Arc<HashSet<String>>);
+
+impl UserPermissions {
+    #[must_use]
+    pub fn has<P: Permission>(&self) -> bool {
+        self.0.contains(P::NAME)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for UserPermissions {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        if let Some(permissions) = parts.extensions.get::<Self>() {
+            return Ok(permissions.clone());
+        }
+        let MaybeUser(user) =
+            MaybeUser::from_request_parts(parts, state).await?;
+        let permissions = match user {
+            Some(user) => load_permissions(user.id)
+                .await
+                .map(|names| Self(Arc::new(names)))
+                .map_err(|e| fail(&parts.headers, e))?,
+            None => Self::default(),
+        };
+        parts.extensions.insert(permissions.clone());
+        Ok(permissions)
+    }
+}
+
+/// The signed in user, when they have permission `P`. Without a user, the
+/// request is rejected like with `AuthUser`, and without the permission
+/// with a 403.
+///
+/// Needs the `sessions` middleware.
+pub struct RequirePermission<P>(pub User, pub PhantomData<P>);
+
+#[async_trait]
+impl<S: Send + Sync, P: Permission> FromRequestParts<S>
+    for RequirePermission<P>
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let AuthUser(user) = AuthUser::from_request_parts(parts, state).await?;
+        let permissions =
+            UserPermissions::from_request_parts(parts, state).await?;
+        if !permissions.has::<P>() {
+            event!(
+                Level::DEBUG,
+                "User {} lacks permission {}",
+                user.id,
+                P::NAME
+            );
+            let message = "You do not have permission to do this";
+            return Err(reject(&parts.headers, message, StatusCode::FORBIDDEN));
+        }
+        Ok(Self(user, PhantomData))
+    }
+}
+
+/// Restricts every route of a router to users with a permission, e.g.
+/// `admin::router().require_permission::<ManageUsers>()` in
+/// `Routes::configure_routes`. Routes added afterwards are not restricted.
+pub trait RequirePermissionLayer {
+    #[must_use]
+    fn require_permission<P: Permission>(self) -> Self;
+}
+
+impl<S: Clone + Send + Sync + 'static> RequirePermissionLayer for Router<S> {
+    fn require_permission<P: Permission>(self) -> Self {
+        // A route layer, so that unknown paths still 404
+        self.route_layer(middleware::from_fn(guard::<P>))
+    }
+}
+
+async fn guard<P: Permission>(
+    _: RequirePermission<P>,
+    request: Request,
+    next: Next,
+) -> Response {
+    next.run(request).await
+}
+
+#[tokio::test]
+async fn test() {
+    use axum::body::Body;
+    use axum::http::header;
+    use axum::routing::get;
+    use chrono::Utc;
+    use tower::ServiceExt;
+
+    struct Manage;
+    impl Permission for Manage {
+        const NAME: &'static str = "things.manage";
+    }
+
+    let app = Router::new()
+        .route("/", get(|| async { "ok" }))
+        .require_permission::<Manage>();
+    // Both are cached in the extensions, so that no database is needed
+    let status = |user: Option<User>, granted: &[&str], json: bool| {
+        let mut request = Request::builder().uri("/");
+        if json {
+            request = request.header(header::ACCEPT, "application/json");
+        }
+        let mut request = request.body(Body::empty()).unwrap();
+        let granted = granted.iter().map(ToString::to_string).collect();
+        request.extensions_mut().insert(MaybeUser(user));
+        request
+            .extensions_mut()
+            .insert(UserPermissions(Arc::new(granted)));
+        let app = app.clone();
+        async move { app.oneshot(request).await.unwrap().status() }
+    };
+    let user = || {
+        Some(User {
+            id: 1,
+            email: "ada@example.com".to_string(),
+            password_hash: String::new(),
+            created_at: Utc::now(),
+        })
+    };
+
+    assert_eq!(status(user(), &["things.manage"], false).await, 200);
+    assert_eq!(status(user(), &["things.read"], false).await, 403);
+    assert_eq!(status(user(), &[], true).await, 403);
+    assert_eq!(status(None, &[], false).await, 303);
+    assert_eq!(status(None, &[], true).await, 401);
+}
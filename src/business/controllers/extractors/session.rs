@@ -0,0 +1,391 @@
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use axum::async_trait;
+use axum::extract::{FromRequestParts, Request, State};
+use axum::http::{header, request::Parts, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum_extra::extract::cookie::{Cookie, CookieJar};
+use chrono::{DateTime, TimeDelta, Utc};
+use custom_errors::err_response::Failed;
+use environment::ENV;
+use rand::distributions::{Alphanumeric, DistString};
+use repositories::SessionStore;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use sqlx::types::Json;
+use tracing::{event, Level};
+use types::entities::SessionRecord;
+
+use super::{fail, should_commit, CookiePolicy};
+
+/// Length of session ids, about 285 bits of randomness.
+const ID_LEN: usize = 48;
+
+/// The stored expiry is only pushed back once it moved by that much, so that
+/// reading a session does not write it on every request.
+const TOUCH_INTERVAL: TimeDelta = TimeDelta::minutes(1);
+
+/// The session of the request, found by the id in the session cookie.
+///
+/// Changes are saved once the handler returned, only when it succeeded or
+/// redirected. That is after the request transaction was committed, and not
+/// in it: when saving fails, the changes are lost and a warning is logged,
+/// but the response is kept, as it reports writes that did happen. Sessions
+/// without data are not stored, so visitors get no cookie until something is
+/// inserted.
+///
+/// Needs the `sessions` middleware.
+#[derive(Clone)]
+pub struct Session(Arc<Mutex<SessionState>>);
+
+struct SessionState {
+    /// `None` until the session is first saved.
+    id: Option<String>,
+    data: Map<String, Value>,
+    created_at: DateTime<Utc>,
+    /// As stored, `None` until the session is first saved.
+    expires_at: Option<DateTime<Utc>>,
+    changed: bool,
+    rotate: bool,
+    destroyed: bool,
+}
+
+impl SessionState {
+    fn new(record: Option<SessionRecord>) -> Self {
+        let (id, data, created_at, expires_at) = match record {
+            Some(record) => (
+                Some(record.id),
+                record.data.0,
+                record.created_at,
+                Some(record.expires_at),
+            ),
+            None => (None, Map::new(), Utc::now(), None),
+        };
+        Self {
+            id,
+            data,
+            created_at,
+            expires_at,
+            changed: false,
+            rotate: false,
+            destroyed: false,
+        }
+    }
+}
+
+impl Session {
+    fn state(&self) -> MutexGuard<'_, SessionState> {
+        // No method panics halfway through a change
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The value stored under `key`, if any.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the stored value is not a `T`.
+    pub fn get<T: DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> serde_json::Result<Option<T>> {
+        let value = self.state().data.get(key).cloned();
+        value.map(serde_json::from_value).transpose()
+    }
+
+    /// Stores `value` under `key`, replacing the previous one.
+    ///
+    /// # Errors
+    ///
+    /// Fails when `value` cannot be serialized to JSON.
+    pub fn insert<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+    ) -> serde_json::Result<()> {
+        let value = serde_json::to_value(value)?;
+        let mut state = self.state();
+        state.data.insert(key.to_string(), value);
+        state.changed = true;
+        drop(state);
+        Ok(())
+    }
+
+    /// Removes the value stored under `key`. The session is deleted once it
+    /// has no data left.
+    pub fn remove(&self, key: &str) {
+        let mut state = self.state();
+        state.changed |= state.data.remove(key).is_some();
+        drop(state);
+    }
+
+    /// Gives the session a new id, keeping its data. Call it whenever
+    /// privileges change (e.g. on login), so that an id planted beforehand
+    /// or leaked at a lower privilege is worthless.
+    pub fn rotate_id(&self) {
+        self.state().rotate = true;
+    }
+
+    /// Deletes the session and its cookie, e.g. on logout.
+    pub fn destroy(&self) {
+        self.state().destroyed = true;
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Session {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<Self>().cloned().ok_or_else(|| {
+            let e = anyhow!("Session used without sessions middleware!");
+            fail(&parts.headers, e)
+        })
+    }
+}
+
+/// The store of the `sessions` middleware, and how it times out sessions.
+#[derive(Clone)]
+pub struct Sessions<S> {
+    store: S,
+    cookie_name: String,
+    policy: CookiePolicy,
+    idle_timeout: TimeDelta,
+    absolute_timeout: TimeDelta,
+}
+
+impl<S: SessionStore> Sessions<S> {
+    /// Sessions in `store`, with the `sessions` and `cookies` settings.
+    #[must_use]
+    pub fn new(store: S) -> Self {
+        let config = &ENV.config.sessions;
+        Self {
+            store,
+            cookie_name: config.cookie_name.clone(),
+            policy: CookiePolicy::from_env(),
+            idle_timeout: time_delta(config.idle_timeout),
+            absolute_timeout: time_delta(config.absolute_timeout),
+        }
+    }
+
+    async fn load(&self, id: &str) -> anyhow::Result<Option<SessionRecord>> {
+        let Some(record) = self.store.load(id).await? else {
+            return Ok(None);
+        };
+        // Only differs from the stored expiry when the timeout was shortened
+        if record.created_at + self.absolute_timeout <= Utc::now() {
+            self.store.delete(id).await?;
+            return Ok(None);
+        }
+        Ok(Some(record))
+    }
+
+    /// Writes what the handler changed, returning the cookie to set, if any.
+    async fn persist(
+        &self,
+        state: SessionState,
+        cookie_sent: bool,
+    ) -> anyhow::Result<Option<Cookie<'static>>> {
+        if state.destroyed || state.data.is_empty() {
+            if let Some(id) = &state.id {
+                self.store.delete(id).await?;
+            }
+            // Also clears the cookie of a session that expired
+            return Ok(cookie_sent.then(|| self.removal_cookie()));
+        }
+
+        let now = Utc::now();
+        let expires_at = (now + self.idle_timeout)
+            .min(state.created_at + self.absolute_timeout);
+        let renewed = state.id.is_none() || state.rotate;
+        let (id, previous_id) = match state.id {
+            Some(id) if !state.rotate => (id, None),
+            previous_id => (new_id(), previous_id),
+        };
+        let touched = state
+            .expires_at
+            .is_none_or(|stored| expires_at - stored >= TOUCH_INTERVAL);
+        if renewed || state.changed || touched {
+            self.store
+                .save(&SessionRecord {
+                    id: id.clone(),
+                    data: Json(state.data),
+                    created_at: state.created_at,
+                    expires_at,
+                })
+                .await?;
+        }
+        // Deleted last, so that a failed save does not lose the session
+        if let Some(previous_id) = previous_id {
+            self.store.delete(&previous_id).await?;
+        }
+
+        let max_age = state.created_at + self.absolute_timeout - now;
+        Ok(renewed.then(|| self.cookie(id, max_age)))
+    }
+
+    fn cookie(&self, id: String, max_age: TimeDelta) -> Cookie<'static> {
+        self.policy
+            .build(self.cookie_name.clone(), id)
+            // Whatever the policy, scripts have no business with the id
+            .http_only(true)
+            .max_age(cookie::time::Duration::seconds(max_age.num_seconds()))
+            .build()
+    }
+
+    fn removal_cookie(&self) -> Cookie<'static> {
+        self.policy.removal(self.cookie_name.clone())
+    }
+}
+
+/// Middleware loading the `Session` before the handler, and saving it
+/// after.
+pub async fn sessions<S: SessionStore>(
+    State(sessions): State<Sessions<S>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let jar = CookieJar::from_headers(request.headers());
+    let sent_id = jar
+        .get(&sessions.cookie_name)
+        .map(|cookie| cookie.value().to_string());
+    // Ids that could not have been issued are not worth a lookup
+    let valid_id = sent_id.as_deref().filter(|id| is_valid(id));
+    let loaded = match valid_id {
+        Some(id) => sessions.load(id).await,
+        None => Ok(None),
+    };
+    let record = match loaded {
+        Ok(record) => record,
+        Err(e) => return fail(request.headers(), e),
+    };
+
+    let session = Session(Arc::new(Mutex::new(SessionState::new(record))));
+    request.extensions_mut().insert(session.clone());
+    let mut response = next.run(request).await;
+
+    let failed = response.extensions().get::<Failed>().is_some();
+    if !should_commit(response.status(), failed) {
+        return response;
+    }
+    // A `Session` kept alive (e.g. moved into a spawned task) is not saved
+    // with changes made from now on
+    let state =
+        std::mem::replace(&mut *session.state(), SessionState::new(None));
+    let persisted = sessions.persist(state, sent_id.is_some()).await;
+    match persisted {
+        Ok(Some(cookie)) => {
+            if let Ok(value) = HeaderValue::from_str(&cookie.to_string()) {
+                response.headers_mut().append(header::SET_COOKIE, value);
+            }
+            response
+        }
+        Ok(None) => response,
+        // The request transaction is committed, an error would hide it
+        Err(e) => {
+            event!(Level::WARN, "Failed to save the session! Error: {e}");
+            response
+        }
+    }
+}
+
+fn new_id() -> String {
+    // `thread_rng` is a CSPRNG
+    Alphanumeric.sample_string(&mut rand::thread_rng(), ID_LEN)
+}
+
+fn is_valid(id: &str) -> bool {
+    id.len() == ID_LEN && id.bytes().all(|b| b.is_ascii_alphanumeric())
+}
+
+fn time_delta(duration: Duration) -> TimeDelta {
+    TimeDelta::from_std(duration).unwrap_or_else(|_| TimeDelta::max_value())
+}
+
+#[tokio::test]
+async fn test() {
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use axum::{middleware, routing::get, Router};
+    use axum_extra::extract::cookie::SameSite;
+    use repositories::MemorySessionStore;
+    use tower::ServiceExt;
+
+    async fn login(session: Session) -> StatusCode {
+        session.insert("user_id", &42).unwrap();
+        session.rotate_id();
+        StatusCode::OK
+    }
+    async fn whoami(session: Session) -> String {
+        format!("{:?}", session.get::<i64>("user_id").unwrap())
+    }
+    async fn logout(session: Session) -> StatusCode {
+        session.destroy();
+        StatusCode::OK
+    }
+
+    let store = MemorySessionStore::default();
+    let sessions = Sessions {
+        store: store.clone(),
+        cookie_name: "session".to_string(),
+        policy: CookiePolicy {
+            domain: "localhost".to_string(),
+            secure: true,
+            http_only: true,
+            same_site: SameSite::Lax,
+        },
+        idle_timeout: TimeDelta::minutes(30),
+        absolute_timeout: TimeDelta::days(1),
+    };
+    let app = Router::new()
+        .route("/login", get(login))
+        .route("/whoami", get(whoami))
+        .route("/logout", get(logout))
+        .layer(middleware::from_fn_with_state(sessions, super::sessions));
+    let call = |uri: &str, cookie: Option<&str>| {
+        let mut request = Request::get(uri);
+        if let Some(cookie) = cookie {
+            request = request.header(header::COOKIE, cookie);
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+    let set_cookie = |response: &Response| {
+        let value = response.headers().get(header::SET_COOKIE)?;
+        let cookie = Cookie::parse(value.to_str().unwrap().to_string());
+        let cookie = cookie.unwrap();
+        Some(format!("{}={}", cookie.name(), cookie.value()))
+    };
+    let body = |response: Response| async {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX);
+        String::from_utf8(bytes.await.unwrap().to_vec()).unwrap()
+    };
+
+    // Anonymous visitors get no session
+    let response = call("/whoami", None).await.unwrap();
+    assert_eq!(set_cookie(&response), None);
+    assert_eq!(body(response).await, "None");
+    assert!(store.is_empty());
+
+    let response = call("/login", None).await.unwrap();
+    let first = set_cookie(&response).unwrap();
+    let response = call("/whoami", Some(&first)).await.unwrap();
+    assert_eq!(set_cookie(&response), None);
+    assert_eq!(body(response).await, "Some(42)");
+
+    // Logging in again rotates the id, the previous one is worthless
+    let response = call("/login", Some(&first)).await.unwrap();
+    let second = set_cookie(&response).unwrap();
+    assert_ne!(first, second);
+    assert_eq!(store.len(), 1);
+    let response = call("/whoami", Some(&first)).await.unwrap();
+    assert_eq!(body(response).await, "None");
+
+    let response = call("/logout", Some(&second)).await.unwrap();
+    assert!(set_cookie(&response).unwrap().ends_with('='));
+    assert!(store.is_empty());
+}
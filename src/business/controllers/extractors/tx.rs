@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use axum::async_trait;
+use axum::extract::{FromRequestParts, Request};
+use axum::http::request::Parts;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use custom_errors::err_response::{ErrResponse, Failed, HtmlKind, JsonKind};
+use repositories::{AppTransaction, Database};
+use sqlx::PgConnection;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tracing::{event, Level};
+
+use super::{fail, is_json, should_commit};
+
+type Slot = Arc<Mutex<Option<AppTransaction>>>;
+
+/// The request transaction, begun on the first call to `conn`. It is
+/// committed once the handler returns a success or redirection status, and
+/// rolled back on an `ErrResponse` or any other status.
+///
+/// Needs the `commit_or_rollback` middleware.
+pub struct Tx(OwnedMutexGuard<Option<AppTransaction>>);
+
+impl Tx {
+    /// Begins the transaction if it was not yet.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the transaction cannot be begun.
+    pub async fn conn(&mut self) -> Result<&mut PgConnection, sqlx::Error> {
+        let tx = match self.0.take() {
+            Some(tx) => tx,
+            None => Database::get_tx().await?,
+        };
+        Ok(self.0.insert(tx))
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Tx {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let headers = &parts.headers;
+        let slot = parts.extensions.get::<Slot>().ok_or_else(|| {
+            fail(headers, anyhow!("Tx used without commit_or_rollback!"))
+        })?;
+        let guard = slot.clone().try_lock_owned().map_err(|_| {
+            fail(headers, anyhow!("Tx extracted twice in the same request!"))
+        })?;
+        Ok(Self(guard))
+    }
+}
+
+/// Middleware ending the transaction `Tx` began, if any, once the handler
+/// returned.
+pub async fn commit_or_rollback(mut request: Request, next: Next) -> Response {
+    let slot: Slot = Arc::default();
+    request.extensions_mut().insert(slot.clone());
+    let response = next.run(request).await;
+
+    // A `Tx` still alive (e.g. moved into a spawned task) rolls back on drop
+    let Some(tx) = slot.try_lock().ok().and_then(|mut tx| tx.take()) else {
+        return response;
+    };
+
+    let failed = response.extensions().get::<Failed>().is_some();
+    if !should_commit(response.status(), failed) {
+        if let Err(e) = tx.rollback().await {
+            event!(Level::ERROR, "Failed to roll back transaction! Error: {e}");
+        }
+        return response;
+    }
+
+    let committed = tx.commit().await;
+    match committed {
+        Ok(()) => response,
+        // The handler's response would lie about the outcome
+        Err(e) if is_json(&response) => {
+            ErrResponse::<JsonKind>::from(e).into_response()
+        }
+        Err(e) => ErrResponse::<HtmlKind>::from(e).into_response(),
+    }
+}
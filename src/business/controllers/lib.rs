@@ -1,6 +1,9 @@
+mod auth;
 mod index;
 mod nested;
 
+pub mod extractors;
+
 use axum::{routing::get, Router};
 
 pub trait Routes {
@@ -11,5 +14,6 @@ impl Routes for Router {
     fn configure_routes(self) -> Self {
         self.route("/", get(index::get))
             .nest("/nested", nested::router())
+            .nest("/auth", auth::router())
     }
 }
@@ -2,22 +2,22 @@ use axum::http::StatusCode;
 
 use axum::response::Json;
 use custom_errors::err_response::{res, JsonResult};
-use environment::ENV;
 use tracing::{event, Level};
 use types::api;
 
 use axum::http::{HeaderMap, HeaderValue};
 use axum::Json as JsonExt;
-use axum_extra::extract::cookie::{Cookie, SameSite};
-use axum_extra::extract::{CookieJar, WithRejection};
+use axum_extra::extract::WithRejection;
 use cookie::time::Duration;
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
 use custom_errors::app_rejection::WithJsonRejection;
 
+use crate::extractors::{cookie, SignedCookies};
+
 pub async fn get(
-    cookie_jar: CookieJar,
+    cookie_jar: SignedCookies,
     WithRejection(JsonExt(json), _): WithJsonRejection<JsonExt<Signature>>,
 ) -> JsonResult {
     if cookie_jar.get("example_cookie").is_some() {
@@ -35,17 +35,13 @@ pub async fn get(
         let response = api::Response::<()>::error(
             format!("Whoops, validation errors! {errors}"),
             None,
-        );
+        )
+        .with_request_id(utils::current_request_id());
         return res((StatusCode::OK, Json(response)));
-    };
+    }
 
-    let cookie = Cookie::build(("example_cookie", "example"))
-        .domain(&*ENV.domain)
-        .path("/")
-        .secure(false)
-        .http_only(false)
+    let cookie = cookie("example_cookie", "example")
         .max_age(Duration::days(30))
-        .same_site(SameSite::None)
         .build();
 
     let cookie_jar = cookie_jar.add(cookie);
@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Exponential backoff with jitter, so that many instances restarting at
+/// once do not hammer the database in lockstep.
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    pub const fn new(initial: Duration, max: Duration) -> Self {
+        Self { initial, max }
+    }
+
+    /// Delay before retrying, after `attempt` failed attempts: a random
+    /// duration between half and all of `initial * 2^(attempt - 1)`, capped
+    /// at `max`.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2_u32.saturating_pow(attempt.saturating_sub(1));
+        let ceiling = self.initial.saturating_mul(factor).min(self.max);
+        rand::thread_rng().gen_range(ceiling / 2..=ceiling)
+    }
+}
+
+#[test]
+fn test() {
+    let backoff =
+        Backoff::new(Duration::from_millis(100), Duration::from_secs(1));
+    for (attempt, ceiling) in
+        [(1, 100), (2, 200), (3, 400), (4, 800), (9, 1000)]
+    {
+        let delay = backoff.delay(attempt);
+        let ceiling = Duration::from_millis(ceiling);
+        assert!(delay >= ceiling / 2 && delay <= ceiling, "{delay:?}");
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::{anyhow, bail};
+use sqlx::postgres::PgArguments;
+use sqlx::{Arguments, Encode, Postgres, Type};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// Conditions, ordering and paging for `Repository::find_many`. Conditions
+/// are joined with `AND`, and values are always bound, never interpolated.
+#[derive(Default)]
+pub struct Filter {
+    conditions: Vec<(&'static str, &'static str)>,
+    order: Vec<(&'static str, Order)>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    arguments: PgArguments,
+    /// First value that failed to encode, reported by `find_many`.
+    error: Option<String>,
+}
+
+impl Filter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn eq<T>(self, column: &'static str, value: T) -> Self
+    where
+        T: for<'q> Encode<'q, Postgres> + Type<Postgres>,
+    {
+        self.compare(column, "=", value)
+    }
+
+    #[must_use]
+    pub fn ne<T>(self, column: &'static str, value: T) -> Self
+    where
+        T: for<'q> Encode<'q, Postgres> + Type<Postgres>,
+    {
+        self.compare(column, "<>", value)
+    }
+
+    #[must_use]
+    pub fn lt<T>(self, column: &'static str, value: T) -> Self
+    where
+        T: for<'q> Encode<'q, Postgres> + Type<Postgres>,
+    {
+        self.compare(column, "<", value)
+    }
+
+    #[must_use]
+    pub fn le<T>(self, column: &'static str, value: T) -> Self
+    where
+        T: for<'q> Encode<'q, Postgres> + Type<Postgres>,
+    {
+        self.compare(column, "<=", value)
+    }
+
+    #[must_use]
+    pub fn gt<T>(self, column: &'static str, value: T) -> Self
+    where
+        T: for<'q> Encode<'q, Postgres> + Type<Postgres>,
+    {
+        self.compare(column, ">", value)
+    }
+
+    #[must_use]
+    pub fn ge<T>(self, column: &'static str, value: T) -> Self
+    where
+        T: for<'q> Encode<'q, Postgres> + Type<Postgres>,
+    {
+        self.compare(column, ">=", value)
+    }
+
+    /// SQL `LIKE`, `%` and `_` being wildcards.
+    #[must_use]
+    pub fn like<T>(self, column: &'static str, pattern: T) -> Self
+    where
+        T: for<'q> Encode<'q, Postgres> + Type<Postgres>,
+    {
+        self.compare(column, "LIKE", pattern)
+    }
+
+    #[must_use]
+    pub fn is_null(mut self, column: &'static str) -> Self {
+        self.conditions.push((column, "IS NULL"));
+        self
+    }
+
+    #[must_use]
+    pub fn is_not_null(mut self, column: &'static str) -> Self {
+        self.conditions.push((column, "IS NOT NULL"));
+        self
+    }
+
+    #[must_use]
+    pub fn order_by(mut self, column: &'static str, order: Order) -> Self {
+        self.order.push((column, order));
+        self
+    }
+
+    #[must_use]
+    pub const fn limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    #[must_use]
+    pub const fn offset(mut self, offset: i64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    fn compare<T>(
+        mut self,
+        column: &'static str,
+        op: &'static str,
+        value: T,
+    ) -> Self
+    where
+        T: for<'q> Encode<'q, Postgres> + Type<Postgres>,
+    {
+        if let Err(e) = self.arguments.add(value) {
+            self.error.get_or_insert_with(|| format!("{column}: {e}"));
+        }
+        self.conditions.push((column, op));
+        self
+    }
+
+    /// Builds the `WHERE`, `ORDER BY`, `LIMIT` and `OFFSET` clauses, with
+    /// a leading space, and takes the values to bind.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a column is not one of `columns`, or a value did not
+    /// encode.
+    pub fn into_sql(
+        self,
+        columns: &[&str],
+    ) -> anyhow::Result<(String, PgArguments)> {
+        if let Some(e) = self.error {
+            bail!("Failed to encode filter value for {e}");
+        }
+        let used = self.conditions.iter().map(|(c, _)| c);
+        let ordered = self.order.iter().map(|(c, _)| c);
+        if let Some(unknown) =
+            used.chain(ordered).find(|c| !columns.contains(c))
+        {
+            return Err(anyhow!("Unknown column `{unknown}` in filter"));
+        }
+
+        let mut sql = String::new();
+        let mut placeholder = 0;
+        for (i, (column, op)) in self.conditions.iter().enumerate() {
+            sql.push_str(if i == 0 { " WHERE " } else { " AND " });
+            if op.starts_with("IS ") {
+                write!(sql, "\"{column}\" {op}")?;
+            } else {
+                placeholder += 1;
+                write!(sql, "\"{column}\" {op} ${placeholder}")?;
+            }
+        }
+        for (i, (column, order)) in self.order.iter().enumerate() {
+            sql.push_str(if i == 0 { " ORDER BY " } else { ", " });
+            let order = match order {
+                Order::Asc => "ASC",
+                Order::Desc => "DESC",
+            };
+            write!(sql, "\"{column}\" {order}")?;
+        }
+        if let Some(limit) = self.limit {
+            write!(sql, " LIMIT {limit}")?;
+        }
+        if let Some(offset) = self.offset {
+            write!(sql, " OFFSET {offset}")?;
+        }
+        Ok((sql, self.arguments))
+    }
+}
+
+#[test]
+fn test() {
+    let columns = ["id", "name", "age", "deleted_at"];
+    let (sql, _) = Filter::new()
+        .eq("name", "Ana")
+        .is_null("deleted_at")
+        .ge("age", 18)
+        .order_by("age", Order::Desc)
+        .order_by("id", Order::Asc)
+        .limit(10)
+        .offset(20)
+        .into_sql(&columns)
+        .unwrap();
+    assert_eq!(
+        sql,
+        " WHERE \"name\" = $1 AND \"deleted_at\" IS NULL AND \"age\" >= $2 \
+         ORDER BY \"age\" DESC, \"id\" ASC LIMIT 10 OFFSET 20"
+    );
+
+    assert_eq!(Filter::new().into_sql(&columns).unwrap().0, "");
+    assert!(Filter::new().eq("name; --", 1).into_sql(&columns).is_err());
+}
@@ -0,0 +1,117 @@
+use serde::Serialize;
+use sqlx::postgres::PgPool;
+use std::time::{Duration, Instant};
+use tokio::time;
+
+use crate::{Database, DB_CONTEXT};
+
+/// How long the primary has to answer `health`, connection acquisition
+/// included.
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Occupancy of a pool, read without querying the database.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct PoolStats {
+    /// Open connections, idle ones included.
+    pub size: u32,
+    pub idle: usize,
+    pub max: u32,
+}
+
+impl PoolStats {
+    fn of(pool: &PgPool) -> Self {
+        Self {
+            size: pool.size(),
+            idle: pool.num_idle(),
+            max: pool.options().get_max_connections(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplicaHealth {
+    pub index: usize,
+    /// As of the last background check.
+    pub healthy: bool,
+    pub pool: PoolStats,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Health {
+    /// Whether the primary answered `SELECT 1` in time.
+    pub reachable: bool,
+    pub error: Option<String>,
+    /// Time spent waiting for a pooled connection.
+    pub acquire_ms: f64,
+    /// Round trip of `SELECT 1`.
+    pub latency_ms: f64,
+    pub pool: PoolStats,
+    pub replicas: Vec<ReplicaHealth>,
+}
+
+impl Database {
+    /// Probes the primary with a bounded `SELECT 1`. Never connects: before
+    /// the first connection, the database is reported unreachable.
+    pub async fn health() -> Health {
+        let Some(context) = DB_CONTEXT.get() else {
+            return Health {
+                reachable: false,
+                error: Some("Not connected yet".to_string()),
+                acquire_ms: 0.0,
+                latency_ms: 0.0,
+                pool: PoolStats::default(),
+                replicas: Vec::new(),
+            };
+        };
+
+        let mut acquire = Duration::ZERO;
+        let mut latency = Duration::ZERO;
+        let probe = async {
+            let start = Instant::now();
+            let mut conn = context.pool.acquire().await?;
+            acquire = start.elapsed();
+            let start = Instant::now();
+            sqlx::query("SELECT 1").execute(&mut *conn).await?;
+            latency = start.elapsed();
+            Ok::<_, sqlx::Error>(())
+        };
+        let error = match time::timeout(HEALTH_TIMEOUT, probe).await {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(_) => Some(format!("No answer within {HEALTH_TIMEOUT:?}")),
+        };
+
+        Health {
+            reachable: error.is_none(),
+            error,
+            acquire_ms: millis(acquire),
+            latency_ms: millis(latency),
+            pool: PoolStats::of(&context.pool),
+            replicas: context
+                .replicas
+                .health()
+                .map(|(index, healthy, pool)| ReplicaHealth {
+                    index,
+                    healthy,
+                    pool: PoolStats::of(pool),
+                })
+                .collect(),
+        }
+    }
+
+    /// Occupancy of the primary pool and then of each replica pool, cheap
+    /// enough to be scraped often. Empty before the first connection.
+    #[must_use]
+    pub fn pool_stats() -> Vec<PoolStats> {
+        DB_CONTEXT.get().map_or_else(Vec::new, |context| {
+            std::iter::once(&context.pool)
+                .chain(context.replicas.pools())
+                .map(PoolStats::of)
+                .collect()
+        })
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
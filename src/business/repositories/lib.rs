@@ -1,12 +1,15 @@
-use async_once::AsyncOnce;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use lazy_static::lazy_static;
-use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::postgres::PgPool;
 use sqlx::{Error, Postgres, Transaction};
-use std::time::Duration;
 use tracing::{event, Level};
 
-use environment::{owned_var, owned_var_or};
-use tokio::{select, time};
+use environment::ENV;
+use tokio::sync::OnceCell;
+use tokio::time;
+use utils::{on_shutdown, Phase};
 
 /// # Loadable<T>
 /// Represents the type of a T that can be loaded from the
@@ -23,55 +26,199 @@ pub type Loadable<T> = anyhow::Result<Option<T>>;
 /// Represents a Postgres transaction
 pub type AppTransaction = Transaction<'static, Postgres>;
 
+mod backoff;
+use backoff::Backoff;
+
+mod filter;
+pub use filter::*;
+
+mod health;
+pub use health::*;
+
+mod repository;
+pub use repository::*;
+
+mod transaction;
+pub use transaction::*;
+
+mod sessions;
+pub use sessions::*;
+
+mod pool;
+use pool::{connect_options, connect_primary, pool_options};
+
+mod replicas;
+use replicas::Replicas;
+
 lazy_static! {
-    static ref DB_CONTEXT: AsyncOnce<Database> =
-        AsyncOnce::new(Database::init());
+    static ref DB_CONTEXT: OnceCell<Database> = OnceCell::new();
 }
 
+/// Set once `connect_until_up` runs, so that requests fail fast rather than
+/// queue behind its attempts.
+static CONNECTING_IN_BACKGROUND: AtomicBool = AtomicBool::new(false);
+
 pub struct Database {
+    /// Primary pool, for writes and transactions.
     pool: PgPool,
+    /// Read replica pools, may be empty.
+    replicas: Replicas,
 }
 impl Database {
-    /// # Panics
+    /// Connects to the primary, retrying with backoff up to the configured
+    /// number of attempts.
+    async fn init() -> Result<Self, Error> {
+        let config = &ENV.config.database;
+        let options = connect_options(config, &config.url)?;
+        let backoff =
+            Backoff::new(config.connect_backoff, config.connect_backoff_max);
+
+        let mut attempt = 1;
+        let pool = loop {
+            let connected = connect_primary(config, &options).await;
+            match connected {
+                Ok(pool) => break pool,
+                Err(e) if attempt < config.connect_attempts => {
+                    let delay = backoff.delay(attempt);
+                    event!(
+                        Level::WARN,
+                        "Failed to connect to Postgres DB (attempt \
+                         {attempt}/{}), retrying in {delay:?}. Error: {e}",
+                        config.connect_attempts
+                    );
+                    time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+
+        // Replicas connect lazily, so that one being down does not prevent
+        // the application from starting.
+        let mut replica_pools = Vec::new();
+        for url in &config.replica_urls {
+            let options = connect_options(config, url)?;
+            replica_pools.push(pool_options(config).connect_lazy_with(options));
+        }
+        let replicas =
+            Replicas::new(replica_pools, config.replica_check_interval).await;
+
+        on_shutdown(Phase::Connections, "database", Self::disconnect);
+        Ok(Self { pool, replicas })
+    }
+
+    /// Connects on first use. Concurrent callers wait for the same attempt.
+    /// While `connect_until_up` is retrying, fails right away instead.
+    ///
+    /// # Errors
     ///
-    /// Panics when connection pool fails to initialize.
-    async fn init() -> Self {
-        let db_url: String = owned_var("DATABASE_URL");
-        let conn_pool_max: u32 = owned_var_or("DB_CONN_POOL_MAX", 100);
-        let pool = PgPoolOptions::new()
-            .max_connections(conn_pool_max)
-            .connect(&db_url)
-            .await
-            .unwrap_or_else(|e| {
-                panic!("Failed to connect to Postgres DB! Error: {e}")
-            });
-
-        Self { pool }
+    /// Fails when the database cannot be reached after every configured
+    /// attempt. The next call will try again.
+    pub async fn try_get() -> Result<&'static Self, Error> {
+        if let Some(context) = DB_CONTEXT.get() {
+            return Ok(context);
+        }
+        if CONNECTING_IN_BACKGROUND.load(Ordering::Acquire) {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "Postgres DB is not connected yet",
+            )));
+        }
+        Self::connect().await
     }
 
+    async fn connect() -> Result<&'static Self, Error> {
+        DB_CONTEXT.get_or_try_init(Self::init).await
+    }
+
+    /// Keeps trying to connect until it succeeds. Meant for degraded starts,
+    /// where the server answers requests before the database is reachable.
+    /// Until then, `try_get` fails without trying to connect.
+    pub async fn connect_until_up() -> &'static Self {
+        CONNECTING_IN_BACKGROUND.store(true, Ordering::Release);
+        loop {
+            let connected = Self::connect().await;
+            match connected {
+                Ok(context) => return context,
+                Err(e) => {
+                    event!(Level::ERROR, "Postgres DB is unreachable: {e}");
+                    time::sleep(ENV.config.database.connect_backoff_max).await;
+                }
+            }
+        }
+    }
+
+    /// Whether a connection to the primary was established.
+    #[must_use]
+    pub fn is_connected() -> bool {
+        DB_CONTEXT.initialized()
+    }
+
+    /// Primary pool. Use it for writes.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the database cannot be reached, see `try_get`.
+    pub async fn try_get_pool() -> Result<&'static PgPool, Error> {
+        Ok(&Self::try_get().await?.pool)
+    }
+
+    /// Primary pool. Use it for writes.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the database cannot be reached, see `try_get_pool`.
     pub async fn get_pool() -> &'static PgPool {
-        &DB_CONTEXT.get().await.pool
+        Self::try_get_pool().await.unwrap_or_else(|e| {
+            panic!("Failed to connect to Postgres DB! Error: {e}")
+        })
     }
 
+    /// Next healthy read replica pool in round-robin order, or the primary
+    /// pool when there is none. Use it for reads that tolerate replication
+    /// lag.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the database cannot be reached, see `try_get`.
+    pub async fn try_get_read_pool() -> Result<&'static PgPool, Error> {
+        let context = Self::try_get().await?;
+        Ok(context.replicas.next_healthy().unwrap_or(&context.pool))
+    }
+
+    /// Next healthy read replica pool, see `try_get_read_pool`.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the database cannot be reached.
+    pub async fn get_read_pool() -> &'static PgPool {
+        Self::try_get_read_pool().await.unwrap_or_else(|e| {
+            panic!("Failed to connect to Postgres DB! Error: {e}")
+        })
+    }
+
+    /// Transaction to commit by hand, prefer `transaction` which commits or
+    /// rolls back on its own.
+    ///
     /// # Errors
     ///
     /// Fails when a transaction cannot be started.
     pub async fn get_tx() -> Result<AppTransaction, Error> {
-        DB_CONTEXT.get().await.pool.begin().await
+        Self::try_get().await?.pool.begin().await
     }
 
-    #[allow(clippy::redundant_pub_crate)] // Select macro propagates this
+    /// Closes every pool, waiting for checked out connections to be
+    /// returned. Registered as a shutdown hook on connection, which bounds
+    /// it with the shutdown budget.
     pub async fn disconnect() {
-        let db_countdown = time::sleep(Duration::from_secs(15));
-        let db_shutdown = async {
-            event!(Level::INFO, "Closing database connections (max. 15s)...");
-            DB_CONTEXT.get().await.pool.close().await;
-            event!(Level::DEBUG, "All database connections closed!");
+        let Some(context) = DB_CONTEXT.get() else {
+            return;
         };
-
-        select! {
-          () = db_countdown => {},
-          () = db_shutdown => {},
+        event!(Level::INFO, "Closing database connections...");
+        context.pool.close().await;
+        for replica in context.replicas.pools() {
+            replica.close().await;
         }
+        event!(Level::DEBUG, "All database connections closed!");
     }
 }
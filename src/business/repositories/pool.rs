@@ -0,0 +1,68 @@
+use environment::{DatabaseConfig, ENV};
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use sqlx::{ConnectOptions, Connection, Error, Executor};
+
+/// Pool options shared by the primary and the replica pools.
+pub fn pool_options(config: &DatabaseConfig) -> PgPoolOptions {
+    PgPoolOptions::new()
+        .max_connections(config.pool_max)
+        .min_connections(config.pool_min)
+        .acquire_timeout(config.acquire_timeout)
+        .idle_timeout(config.idle_timeout)
+        .max_lifetime(config.max_lifetime)
+        .test_before_acquire(config.test_before_acquire)
+        .after_connect(|conn, _meta| {
+            Box::pin(async move {
+                let config = &ENV.config.database;
+                let settings = [
+                    ("application_name", Some(config.application_name.clone())),
+                    ("search_path", config.search_path.clone()),
+                    (
+                        "statement_timeout",
+                        config
+                            .statement_timeout
+                            .map(|t| format!("{}ms", t.as_millis())),
+                    ),
+                ];
+                for (name, value) in settings {
+                    let Some(value) = value else { continue };
+                    sqlx::query("SELECT set_config($1, $2, false)")
+                        .bind(name)
+                        .bind(value)
+                        .execute(&mut *conn)
+                        .await?;
+                }
+                if let Some(sql) = &config.after_connect_sql {
+                    conn.execute(sqlx::raw_sql(sql)).await?;
+                }
+                Ok(())
+            })
+        })
+}
+
+/// # Errors
+///
+/// Fails when `url` is not a valid connection string.
+pub fn connect_options(
+    config: &DatabaseConfig,
+    url: &str,
+) -> Result<PgConnectOptions, Error> {
+    Ok(url
+        .parse::<PgConnectOptions>()?
+        .statement_cache_capacity(config.statement_cache_capacity))
+}
+
+/// Connects the primary pool. The pool keeps retrying until its acquire
+/// timeout, so a single connection is tried first to fail fast.
+///
+/// # Errors
+///
+/// Fails when the database cannot be reached.
+pub async fn connect_primary(
+    config: &DatabaseConfig,
+    options: &PgConnectOptions,
+) -> Result<PgPool, Error> {
+    let probe = options.connect().await?;
+    probe.close().await?;
+    pool_options(config).connect_with(options.clone()).await
+}
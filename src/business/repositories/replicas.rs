@@ -0,0 +1,91 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::postgres::PgPool;
+use tokio::time;
+use tracing::{event, Level};
+
+/// How long a replica has to answer a health check.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+struct Replica {
+    index: usize,
+    pool: PgPool,
+    healthy: AtomicBool,
+}
+
+/// Read replicas, used in round-robin while they answer health checks.
+pub struct Replicas {
+    replicas: Arc<[Replica]>,
+    next: AtomicUsize,
+}
+
+impl Replicas {
+    /// Checks every replica once, then keeps checking them in the background
+    /// every `interval`, until their pools are closed.
+    pub async fn new(pools: Vec<PgPool>, interval: Duration) -> Self {
+        let replicas: Arc<[Replica]> = pools
+            .into_iter()
+            .enumerate()
+            .map(|(index, pool)| Replica {
+                index,
+                pool,
+                healthy: AtomicBool::new(false),
+            })
+            .collect();
+
+        check_all(&replicas).await;
+        if !replicas.is_empty() {
+            let watched = Arc::clone(&replicas);
+            tokio::spawn(async move {
+                while !watched.iter().all(|r| r.pool.is_closed()) {
+                    time::sleep(interval).await;
+                    check_all(&watched).await;
+                }
+            });
+        }
+
+        Self {
+            replicas,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// The next healthy replica in round-robin order, if any.
+    pub fn next_healthy(&self) -> Option<&PgPool> {
+        let len = self.replicas.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..len)
+            .map(|i| &self.replicas[(start + i) % len])
+            .find(|r| r.healthy.load(Ordering::Relaxed))
+            .map(|r| &r.pool)
+    }
+
+    pub fn pools(&self) -> impl Iterator<Item = &PgPool> {
+        self.replicas.iter().map(|r| &r.pool)
+    }
+
+    /// Index, health as of the last check, and pool of every replica.
+    pub fn health(&self) -> impl Iterator<Item = (usize, bool, &PgPool)> {
+        self.replicas
+            .iter()
+            .map(|r| (r.index, r.healthy.load(Ordering::Relaxed), &r.pool))
+    }
+}
+
+async fn check_all(replicas: &[Replica]) {
+    for replica in replicas.iter().filter(|r| !r.pool.is_closed()) {
+        let query = sqlx::query("SELECT 1").execute(&replica.pool);
+        let healthy = time::timeout(CHECK_TIMEOUT, query)
+            .await
+            .is_ok_and(|r| r.is_ok());
+
+        let index = replica.index;
+        match (replica.healthy.swap(healthy, Ordering::Relaxed), healthy) {
+            (false, true) => event!(Level::INFO, "DB replica #{index} is up"),
+            (true, false) => event!(Level::WARN, "DB replica #{index} is down"),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::bail;
+use sqlx::{PgConnection, PgPool};
+use std::future::Future;
+use types::entities::Entity;
+
+use crate::{Filter, Loadable};
+
+/// CRUD for any `Entity`, on anything queries run on: a pool, a connection,
+/// or a transaction (e.g. `pool.find_by_id(&id)` or `tx.insert(&user)`).
+pub trait Repository<E: Entity> {
+    fn find_by_id(self, id: &E::Id)
+        -> impl Future<Output = Loadable<E>> + Send;
+
+    fn find_many(
+        self,
+        filter: Filter,
+    ) -> impl Future<Output = anyhow::Result<Vec<E>>> + Send;
+
+    /// Inserts `entity`, returning the row with the generated columns.
+    fn insert(
+        self,
+        entity: &E,
+    ) -> impl Future<Output = anyhow::Result<E>> + Send;
+
+    /// Updates the row with the id of `entity`, returning it, or `None` when
+    /// there is no such row.
+    fn update(self, entity: &E) -> impl Future<Output = Loadable<E>> + Send;
+
+    /// Whether a row was deleted.
+    fn delete(
+        self,
+        id: &E::Id,
+    ) -> impl Future<Output = anyhow::Result<bool>> + Send;
+
+    fn exists(
+        self,
+        id: &E::Id,
+    ) -> impl Future<Output = anyhow::Result<bool>> + Send;
+}
+
+/// Transactions deref to `PgConnection`, so they are covered as well. Tables
+/// are quoted like columns, as the default of a `User` entity is `user`.
+macro_rules! impl_repository {
+    ($($executor:ty),*) => {$(
+        impl<E: Entity> Repository<E> for $executor {
+            async fn find_by_id(self, id: &E::Id) -> Loadable<E> {
+                let sql = format!(
+                    "SELECT {} FROM \"{}\" WHERE \"{}\" = $1",
+                    columns(E::COLUMNS),
+                    E::TABLE,
+                    E::ID
+                );
+                Ok(sqlx::query_as(&sql).bind(id).fetch_optional(self).await?)
+            }
+
+            async fn find_many(self, filter: Filter) -> anyhow::Result<Vec<E>> {
+                let (clauses, arguments) = filter.into_sql(E::COLUMNS)?;
+                let sql = format!(
+                    "SELECT {} FROM \"{}\"{clauses}",
+                    columns(E::COLUMNS),
+                    E::TABLE
+                );
+                Ok(sqlx::query_as_with(&sql, arguments).fetch_all(self).await?)
+            }
+
+            async fn insert(self, entity: &E) -> anyhow::Result<E> {
+                let sql = if E::INSERT_COLUMNS.is_empty() {
+                    format!(
+                        "INSERT INTO \"{}\" DEFAULT VALUES RETURNING {}",
+                        E::TABLE,
+                        columns(E::COLUMNS)
+                    )
+                } else {
+                    format!(
+                        "INSERT INTO \"{}\" ({}) VALUES ({}) RETURNING {}",
+                        E::TABLE,
+                        columns(E::INSERT_COLUMNS),
+                        placeholders(E::INSERT_COLUMNS.len()),
+                        columns(E::COLUMNS)
+                    )
+                };
+                let query = entity.bind_insert(sqlx::query_as(&sql));
+                Ok(query.fetch_one(self).await?)
+            }
+
+            async fn update(self, entity: &E) -> Loadable<E> {
+                if E::UPDATE_COLUMNS.is_empty() {
+                    bail!("{} has no column to update", E::TABLE);
+                }
+                let assignments: Vec<String> = E::UPDATE_COLUMNS
+                    .iter()
+                    .enumerate()
+                    .map(|(i, column)| format!("\"{column}\" = ${}", i + 1))
+                    .collect();
+                let sql = format!(
+                    "UPDATE \"{}\" SET {} WHERE \"{}\" = ${} RETURNING {}",
+                    E::TABLE,
+                    assignments.join(", "),
+                    E::ID,
+                    E::UPDATE_COLUMNS.len() + 1,
+                    columns(E::COLUMNS)
+                );
+                let query = entity.bind_update(sqlx::query_as(&sql));
+                let query = query.bind(entity.id());
+                Ok(query.fetch_optional(self).await?)
+            }
+
+            async fn delete(self, id: &E::Id) -> anyhow::Result<bool> {
+                let sql = format!(
+                    "DELETE FROM \"{}\" WHERE \"{}\" = $1",
+                    E::TABLE,
+                    E::ID
+                );
+                let result = sqlx::query(&sql).bind(id).execute(self).await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn exists(self, id: &E::Id) -> anyhow::Result<bool> {
+                let sql = format!(
+                    "SELECT EXISTS(SELECT 1 FROM \"{}\" WHERE \"{}\" = $1)",
+                    E::TABLE,
+                    E::ID
+                );
+                Ok(sqlx::query_scalar(&sql).bind(id).fetch_one(self).await?)
+            }
+        }
+    )*};
+}
+impl_repository!(&PgPool, &mut PgConnection);
+
+/// `"a", "b"`, quoted so that columns may be keywords.
+fn columns(columns: &[&str]) -> String {
+    let quoted: Vec<String> = columns
+        .iter()
+        .map(|column| format!("\"{column}\""))
+        .collect();
+    quoted.join(", ")
+}
+
+/// `$1, ..., $count`.
+fn placeholders(count: usize) -> String {
+    let numbered: Vec<String> = (1..=count).map(|i| format!("${i}")).collect();
+    numbered.join(", ")
+}
+
+#[test]
+fn test() {
+    assert_eq!(columns(&["id", "user"]), "\"id\", \"user\"");
+    assert_eq!(placeholders(3), "$1, $2, $3");
+}
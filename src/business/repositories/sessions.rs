@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::time::{self, Instant, MissedTickBehavior};
+use tracing::{event, Level};
+use types::entities::SessionRecord;
+use utils::{on_shutdown, Phase};
+
+use crate::{Database, Loadable, Repository};
+
+/// Where server-side sessions are kept, see `controllers::extractors::Session`.
+pub trait SessionStore: Clone + Send + Sync + 'static {
+    /// The session with `id`, or `None` when there is none or it expired.
+    fn load(
+        &self,
+        id: &str,
+    ) -> impl Future<Output = Loadable<SessionRecord>> + Send;
+
+    /// Inserts the session, or replaces the data and expiry of the one with
+    /// the same id.
+    fn save(
+        &self,
+        record: &SessionRecord,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    fn delete(
+        &self,
+        id: &str,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// Deletes every expired session, returning how many there were.
+    fn delete_expired(
+        &self,
+    ) -> impl Future<Output = anyhow::Result<u64>> + Send;
+}
+
+/// Sessions in the `sessions` table of the primary.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PgSessionStore;
+
+impl SessionStore for PgSessionStore {
+    async fn load(&self, id: &str) -> Loadable<SessionRecord> {
+        let pool = Database::try_get_pool().await?;
+        let record: Option<SessionRecord> =
+            pool.find_by_id(&id.to_string()).await?;
+        Ok(record.filter(|record| record.expires_at > Utc::now()))
+    }
+
+    async fn save(&self, record: &SessionRecord) -> anyhow::Result<()> {
+        let pool = Database::try_get_pool().await?;
+        sqlx::query(
+            "INSERT INTO sessions (id, data, created_at, expires_at) \
+             VALUES ($1, $2, $3, $4) \
+             ON CONFLICT (id) DO UPDATE \
+             SET data = EXCLUDED.data, expires_at = EXCLUDED.expires_at",
+        )
+        .bind(&record.id)
+        .bind(&record.data)
+        .bind(record.created_at)
+        .bind(record.expires_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> anyhow::Result<()> {
+        let pool = Database::try_get_pool().await?;
+        Repository::<SessionRecord>::delete(pool, &id.to_string()).await?;
+        Ok(())
+    }
+
+    async fn delete_expired(&self) -> anyhow::Result<u64> {
+        let pool = Database::try_get_pool().await?;
+        let result =
+            sqlx::query("DELETE FROM sessions WHERE expires_at <= now()")
+                .execute(pool)
+                .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+/// Sessions kept in memory, and lost on restart. Meant for tests.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySessionStore(Arc<Mutex<HashMap<String, SessionRecord>>>);
+
+impl MemorySessionStore {
+    fn sessions(&self) -> MutexGuard<'_, HashMap<String, SessionRecord>> {
+        // A panic while holding the lock cannot leave the map half updated
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// How many sessions are stored, expired ones included.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.sessions().len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    async fn load(&self, id: &str) -> Loadable<SessionRecord> {
+        let sessions = self.sessions();
+        let record = sessions.get(id).cloned();
+        drop(sessions);
+        Ok(record.filter(|record| record.expires_at > Utc::now()))
+    }
+
+    async fn save(&self, record: &SessionRecord) -> anyhow::Result<()> {
+        self.sessions().insert(record.id.clone(), record.clone());
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> anyhow::Result<()> {
+        self.sessions().remove(id);
+        Ok(())
+    }
+
+    async fn delete_expired(&self) -> anyhow::Result<u64> {
+        let now = Utc::now();
+        let mut sessions = self.sessions();
+        let before = sessions.len();
+        sessions.retain(|_, record| record.expires_at > now);
+        let deleted = before - sessions.len();
+        drop(sessions);
+        Ok(deleted as u64)
+    }
+}
+
+/// Deletes the expired sessions of `store` every `interval` in the
+/// background, until shutdown. Expired sessions are never loaded, this only
+/// keeps the store from growing.
+pub fn spawn_sweeper<S: SessionStore>(store: S, interval: Duration) {
+    let sweeper = tokio::spawn(async move {
+        let mut ticks = time::interval_at(Instant::now() + interval, interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            let swept = store.delete_expired().await;
+            match swept {
+                Ok(0) => {}
+                Ok(count) => {
+                    event!(Level::DEBUG, "Deleted {count} expired sessions");
+                }
+                Err(e) => event!(
+                    Level::WARN,
+                    "Failed to delete expired sessions! Error: {e}"
+                ),
+            }
+        }
+    });
+    on_shutdown(Phase::Jobs, "session sweeper", move || async move {
+        sweeper.abort();
+    });
+}
+
+#[tokio::test]
+async fn test() {
+    use chrono::TimeDelta;
+    use sqlx::types::Json;
+
+    let store = MemorySessionStore::default();
+    let record = |id: &str, expires_in: TimeDelta| SessionRecord {
+        id: id.to_string(),
+        data: Json(serde_json::Map::new()),
+        created_at: Utc::now(),
+        expires_at: Utc::now() + expires_in,
+    };
+    store
+        .save(&record("live", TimeDelta::minutes(5)))
+        .await
+        .unwrap();
+    store
+        .save(&record("expired", -TimeDelta::minutes(5)))
+        .await
+        .unwrap();
+
+    assert!(store.load("live").await.unwrap().is_some());
+    assert!(store.load("expired").await.unwrap().is_none());
+    assert!(store.load("unknown").await.unwrap().is_none());
+
+    assert_eq!(store.delete_expired().await.unwrap(), 1);
+    assert_eq!(store.len(), 1);
+    store.delete("live").await.unwrap();
+    assert!(store.is_empty());
+}
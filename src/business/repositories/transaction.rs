@@ -0,0 +1,200 @@
+use sqlx::{Connection, PgConnection};
+use std::time::Duration;
+use tokio::time;
+use tracing::{event, Level};
+
+use environment::ENV;
+
+use crate::{Backoff, Database};
+
+/// SQLSTATEs worth retrying the whole transaction for.
+const SERIALIZATION_FAILURE: &str = "40001";
+const DEADLOCK_DETECTED: &str = "40P01";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Isolation {
+    /// Postgres' default.
+    #[default]
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
+}
+
+impl Isolation {
+    const fn level(self) -> &'static str {
+        match self {
+            Self::ReadCommitted => "READ COMMITTED",
+            Self::RepeatableRead => "REPEATABLE READ",
+            Self::Serializable => "SERIALIZABLE",
+        }
+    }
+}
+
+impl Database {
+    /// Runs `f` in a transaction on the primary, see `transaction_with`.
+    ///
+    /// # Errors
+    ///
+    /// Fails when `f` fails, or when the transaction cannot be started or
+    /// committed.
+    pub async fn transaction<T, F>(f: F) -> anyhow::Result<T>
+    where
+        F: AsyncFnMut(&mut PgConnection) -> anyhow::Result<T>,
+    {
+        Self::transaction_with(Isolation::default(), f).await
+    }
+
+    /// Runs `f` in a transaction on the primary, committing when it returns
+    /// `Ok` and rolling back when it returns `Err`.
+    ///
+    /// On serialization failures and deadlocks, the whole transaction is
+    /// retried up to `DB_TX_RETRIES` times, so `f` must be safe to run
+    /// again.
+    ///
+    /// # Errors
+    ///
+    /// Fails when `f` fails, or when the transaction cannot be started or
+    /// committed.
+    pub async fn transaction_with<T, F>(
+        isolation: Isolation,
+        mut f: F,
+    ) -> anyhow::Result<T>
+    where
+        F: AsyncFnMut(&mut PgConnection) -> anyhow::Result<T>,
+    {
+        let pool = Self::try_get_pool().await?;
+        let retries = ENV.config.database.tx_retries;
+        let backoff =
+            Backoff::new(Duration::from_millis(10), Duration::from_secs(1));
+
+        let mut attempt = 0;
+        loop {
+            let result = async {
+                let mut tx = pool.begin().await?;
+                let level = isolation.level();
+                let sql = format!("SET TRANSACTION ISOLATION LEVEL {level}");
+                sqlx::query(&sql).execute(&mut *tx).await?;
+                let result = f(&mut tx).await;
+                match result {
+                    Ok(value) => {
+                        tx.commit().await?;
+                        Ok(value)
+                    }
+                    Err(e) => {
+                        rollback(tx.rollback().await);
+                        Err(e)
+                    }
+                }
+            }
+            .await;
+
+            match result {
+                Err(e) if attempt < retries && is_retryable(&e) => {
+                    attempt += 1;
+                    let delay = backoff.delay(attempt);
+                    event!(
+                        Level::WARN,
+                        "Retrying transaction ({attempt}/{retries}) in \
+                         {delay:?}. Error: {e}"
+                    );
+                    time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Runs `f` in a savepoint of the transaction `conn` is in, releasing it when
+/// `f` returns `Ok` and rolling back to it when `f` returns `Err`. The outer
+/// transaction can go on either way.
+///
+/// # Errors
+///
+/// Fails when `f` fails, or when the savepoint cannot be created or released.
+pub async fn savepoint<T, F>(conn: &mut PgConnection, f: F) -> anyhow::Result<T>
+where
+    F: AsyncFnOnce(&mut PgConnection) -> anyhow::Result<T>,
+{
+    let mut savepoint = conn.begin().await?;
+    let result = f(&mut savepoint).await;
+    match result {
+        Ok(value) => {
+            savepoint.commit().await?;
+            Ok(value)
+        }
+        Err(e) => {
+            rollback(savepoint.rollback().await);
+            Err(e)
+        }
+    }
+}
+
+/// A failed rollback is only logged, the error that caused it matters more.
+/// The connection is closed by sqlx in that case.
+fn rollback(result: Result<(), sqlx::Error>) {
+    if let Err(e) = result {
+        event!(Level::ERROR, "Failed to roll back transaction! Error: {e}");
+    }
+}
+
+/// Whether `e` comes from a serialization failure or a deadlock.
+fn is_retryable(e: &anyhow::Error) -> bool {
+    e.chain()
+        .filter_map(|cause| cause.downcast_ref::<sqlx::Error>())
+        .filter_map(sqlx::Error::as_database_error)
+        .filter_map(sqlx::error::DatabaseError::code)
+        .any(|code| code == SERIALIZATION_FAILURE || code == DEADLOCK_DETECTED)
+}
+
+#[test]
+fn test() {
+    use std::borrow::Cow;
+    use std::error::Error;
+    use std::fmt;
+
+    use sqlx::error::{DatabaseError, ErrorKind};
+
+    /// A database error with only a SQLSTATE, as Postgres would send it.
+    #[derive(Debug)]
+    struct State(&'static str);
+    impl fmt::Display for State {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "SQLSTATE {}", self.0)
+        }
+    }
+    impl Error for State {}
+    impl DatabaseError for State {
+        fn message(&self) -> &str {
+            self.0
+        }
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed(self.0))
+        }
+        fn as_error(&self) -> &(dyn Error + Send + Sync + 'static) {
+            self
+        }
+        fn as_error_mut(&mut self) -> &mut (dyn Error + Send + Sync + 'static) {
+            self
+        }
+        fn into_error(
+            self: Box<Self>,
+        ) -> Box<dyn Error + Send + Sync + 'static> {
+            self
+        }
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Other
+        }
+    }
+    let error = |code| {
+        anyhow::Error::from(sqlx::Error::Database(Box::new(State(code))))
+    };
+
+    assert!(is_retryable(&error(SERIALIZATION_FAILURE)));
+    assert!(is_retryable(&error(DEADLOCK_DETECTED)));
+    // However deep in the chain
+    assert!(is_retryable(&error("40001").context("Failed to update")));
+    assert!(!is_retryable(&error("23505")));
+    assert!(!is_retryable(&anyhow::anyhow!("Not a database error")));
+    assert!(!is_retryable(&sqlx::Error::RowNotFound.into()));
+}
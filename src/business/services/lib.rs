@@ -1 +1,11 @@
+mod passwords;
+pub use passwords::*;
 
+mod users;
+pub use users::*;
+
+mod roles;
+pub use roles::*;
+
+mod tokens;
+pub use tokens::*;
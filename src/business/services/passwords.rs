@@ -0,0 +1,135 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{self, PasswordHash, SaltString};
+use argon2::{Algorithm, Argon2, Params, PasswordHasher, PasswordVerifier};
+use environment::ENV;
+use lazy_static::lazy_static;
+use tokio::task;
+
+lazy_static! {
+    static ref ARGON2: Argon2<'static> = {
+        let config = &ENV.config.passwords;
+        argon2(config.memory_kib, config.iterations, config.parallelism)
+            .unwrap_or_else(|e| panic!("Invalid Argon2 parameters: {e}"))
+    };
+    /// Verified against when there is no user, so that failing to log in
+    /// takes as long whether the email exists or not.
+    static ref DUMMY_HASH: String = hash_with(&ARGON2, "")
+        .unwrap_or_else(|e| panic!("Failed to hash dummy password: {e}"));
+}
+
+/// Outcome of `verify_password`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordCheck {
+    Mismatch,
+    /// `rehash` when the hash was made with other parameters than the
+    /// configured ones, and should be replaced.
+    Match {
+        rehash: bool,
+    },
+}
+
+/// Hashes `password` with Argon2id, on a blocking thread.
+///
+/// # Errors
+///
+/// Fails when the hashing thread panics, which it should not.
+pub async fn hash_password(password: String) -> anyhow::Result<String> {
+    Ok(task::spawn_blocking(move || hash_with(&ARGON2, &password)).await??)
+}
+
+/// Checks `password` against `hash`, on a blocking thread.
+///
+/// # Errors
+///
+/// Fails when `hash` is not a valid PHC string.
+pub async fn verify_password(
+    password: String,
+    hash: String,
+) -> anyhow::Result<PasswordCheck> {
+    let check = move || verify_with(&ARGON2, &password, &hash);
+    Ok(task::spawn_blocking(check).await??)
+}
+
+/// Spends as long as `verify_password` does, for a user that does not
+/// exist.
+///
+/// # Errors
+///
+/// Fails when the hashing thread panics, which it should not.
+pub async fn verify_dummy_password(password: String) -> anyhow::Result<()> {
+    let check = move || verify_with(&ARGON2, &password, &DUMMY_HASH);
+    task::spawn_blocking(check).await??;
+    Ok(())
+}
+
+fn argon2(
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+) -> Result<Argon2<'static>, argon2::Error> {
+    let params = Params::new(memory_kib, iterations, parallelism, None)?;
+    Ok(Argon2::new(
+        Algorithm::Argon2id,
+        argon2::Version::V0x13,
+        params,
+    ))
+}
+
+fn hash_with(
+    argon2: &Argon2<'_>,
+    password: &str,
+) -> Result<String, password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(argon2
+        .hash_password(password.as_bytes(), &salt)?
+        .to_string())
+}
+
+fn verify_with(
+    argon2: &Argon2<'_>,
+    password: &str,
+    hash: &str,
+) -> Result<PasswordCheck, password_hash::Error> {
+    let parsed = PasswordHash::new(hash)?;
+    // Hashes of another algorithm are verified with their own parameters
+    match argon2.verify_password(password.as_bytes(), &parsed) {
+        Ok(()) => Ok(PasswordCheck::Match {
+            rehash: is_outdated(argon2, &parsed),
+        }),
+        Err(password_hash::Error::Password) => Ok(PasswordCheck::Mismatch),
+        Err(e) => Err(e),
+    }
+}
+
+fn is_outdated(argon2: &Argon2<'_>, hash: &PasswordHash<'_>) -> bool {
+    let current = argon2.params();
+    hash.algorithm != Algorithm::Argon2id.ident()
+        || hash.version != Some(argon2::Version::V0x13.into())
+        || Params::try_from(hash).map_or(true, |params| {
+            params.m_cost() != current.m_cost()
+                || params.t_cost() != current.t_cost()
+                || params.p_cost() != current.p_cost()
+        })
+}
+
+#[test]
+fn test() {
+    let cheap = argon2(8, 1, 1).unwrap();
+    let hash = hash_with(&cheap, "hunter22").unwrap();
+    assert!(hash.starts_with("$argon2id$v=19$m=8,t=1,p=1$"));
+
+    let check = |argon2, password| verify_with(argon2, password, &hash);
+    assert_eq!(
+        check(&cheap, "hunter22").unwrap(),
+        PasswordCheck::Match { rehash: false }
+    );
+    assert_eq!(check(&cheap, "hunter23").unwrap(), PasswordCheck::Mismatch);
+
+    // Made before the cost was raised
+    let costlier = argon2(16, 2, 1).unwrap();
+    assert_eq!(
+        check(&costlier, "hunter22").unwrap(),
+        PasswordCheck::Match { rehash: true }
+    );
+    assert!(verify_with(&cheap, "hunter22", "not a hash").is_err());
+}
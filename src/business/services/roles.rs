@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+
+use sqlx::{PgConnection, PgExecutor};
+
+/// Names of the permissions of the user, through all of their roles.
+///
+/// # Errors
+///
+/// Fails when the query fails.
+pub async fn permissions_of(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+) -> anyhow::Result<HashSet<String>> {
+    let names: Vec<String> = sqlx::query_scalar(
+        "SELECT DISTINCT p.name FROM permissions p \
+         JOIN role_permissions rp ON rp.permission_id = p.id \
+         JOIN user_roles ur ON ur.role_id = rp.role_id \
+         WHERE ur.user_id = $1",
+    )
+    .bind(user_id)
+    .fetch_all(executor)
+    .await?;
+    Ok(names.into_iter().collect())
+}
+
+/// Gives the role named `role` to the user. Returns `false` when there is
+/// no such role, or when the user already has it.
+///
+/// # Errors
+///
+/// Fails when the query fails, e.g. when there is no such user.
+pub async fn assign_role(
+    conn: &mut PgConnection,
+    user_id: i64,
+    role: &str,
+) -> anyhow::Result<bool> {
+    let result = sqlx::query(
+        "INSERT INTO user_roles (user_id, role_id) \
+         SELECT $1, id FROM roles WHERE name = $2 \
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(role)
+    .execute(conn)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Takes the role named `role` from the user. Returns `false` when the user
+/// did not have it.
+///
+/// # Errors
+///
+/// Fails when the query fails.
+pub async fn revoke_role(
+    conn: &mut PgConnection,
+    user_id: i64,
+    role: &str,
+) -> anyhow::Result<bool> {
+    let result = sqlx::query(
+        "DELETE FROM user_roles WHERE user_id = $1 \
+         AND role_id = (SELECT id FROM roles WHERE name = $2)",
+    )
+    .bind(user_id)
+    .bind(role)
+    .execute(conn)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Gives `permission` to the role named `role`, creating either when
+/// missing.
+///
+/// # Errors
+///
+/// Fails when the query fails.
+pub async fn grant_permission(
+    conn: &mut PgConnection,
+    role: &str,
+    permission: &str,
+) -> anyhow::Result<()> {
+    // The no-op updates make `RETURNING` yield existing rows too
+    sqlx::query(
+        "WITH r AS ( \
+             INSERT INTO roles (name) VALUES ($1) \
+             ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name \
+             RETURNING id \
+         ), p AS ( \
+             INSERT INTO permissions (name) VALUES ($2) \
+             ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name \
+             RETURNING id \
+         ) \
+         INSERT INTO role_permissions (role_id, permission_id) \
+         SELECT r.id, p.id FROM r, p ON CONFLICT DO NOTHING",
+    )
+    .bind(role)
+    .bind(permission)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
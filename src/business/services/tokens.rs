@@ -0,0 +1,242 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use rand::distributions::{Alphanumeric, DistString};
+use repositories::{Filter, Order, Repository};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
+use types::entities::{ApiToken, User};
+
+use crate::permissions_of;
+
+/// Starts every token, so that leaked ones are easy to scan for.
+const TOKEN_PREFIX: &str = "pat_";
+/// Random characters after the prefix, about 238 bits.
+const TOKEN_LEN: usize = 40;
+/// How stale `last_used_at` may get, so that a busy token is not written on
+/// every request.
+const TOUCH_INTERVAL: TimeDelta = TimeDelta::minutes(1);
+/// Longest scope name. Scopes are permission names, which are short.
+const MAX_SCOPE_LEN: usize = 100;
+
+/// A token just created. `token` is not stored, and cannot be shown again.
+pub struct NewToken {
+    pub token: String,
+    pub record: ApiToken,
+}
+
+/// Why `create_token` refused the scopes it was asked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScopeError {
+    TooLong,
+    /// Unknown, or a permission the user does not have.
+    NotGranted(String),
+}
+
+impl fmt::Display for ScopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLong => {
+                write!(f, "Scopes are at most {MAX_SCOPE_LEN} characters long")
+            }
+            Self::NotGranted(scope) => {
+                write!(f, "You do not have the {scope} permission")
+            }
+        }
+    }
+}
+
+/// Checks that every scope is a permission in `granted`, so that a token
+/// never allows more than its user may do.
+fn check_scopes(
+    scopes: &[String],
+    granted: &HashSet<String>,
+) -> Result<(), ScopeError> {
+    for scope in scopes {
+        if scope.len() > MAX_SCOPE_LEN {
+            return Err(ScopeError::TooLong);
+        }
+        if !granted.contains(scope) {
+            return Err(ScopeError::NotGranted(scope.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// Creates a token for the user, with the given scopes. Each scope is a
+/// permission the user must have, see `check_scopes`.
+///
+/// # Errors
+///
+/// Fails when a query fails, e.g. when there is no such user.
+pub async fn create_token(
+    conn: &mut PgConnection,
+    user_id: i64,
+    name: String,
+    mut scopes: Vec<String>,
+    expires_at: Option<DateTime<Utc>>,
+) -> anyhow::Result<Result<NewToken, ScopeError>> {
+    scopes.sort_unstable();
+    scopes.dedup();
+    let granted = permissions_of(&mut *conn, user_id).await?;
+    if let Err(e) = check_scopes(&scopes, &granted) {
+        return Ok(Err(e));
+    }
+    let token = generate_token();
+    let record = ApiToken {
+        id: 0,
+        user_id,
+        name,
+        token_hash: hash_token(&token),
+        scopes,
+        created_at: Utc::now(),
+        last_used_at: None,
+        expires_at,
+    };
+    let record = conn.insert(&record).await?;
+    Ok(Ok(NewToken { token, record }))
+}
+
+/// Tokens of the user, newest first, expired ones included.
+///
+/// # Errors
+///
+/// Fails when the query fails.
+pub async fn list_tokens(
+    conn: &mut PgConnection,
+    user_id: i64,
+) -> anyhow::Result<Vec<ApiToken>> {
+    let filter = Filter::new()
+        .eq("user_id", user_id)
+        .order_by("id", Order::Desc);
+    conn.find_many(filter).await
+}
+
+/// Deletes the token with `id`, if it is one of the user's. Returns whether
+/// there was one.
+///
+/// # Errors
+///
+/// Fails when the query fails.
+pub async fn revoke_token(
+    conn: &mut PgConnection,
+    user_id: i64,
+    id: i64,
+) -> anyhow::Result<bool> {
+    let result =
+        sqlx::query("DELETE FROM api_tokens WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(conn)
+            .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// The token sent by a client, with its user, or `None` when it is unknown
+/// or expired. Records when it was last used.
+///
+/// The scopes of the token are as created. Whether the user still has
+/// those permissions is for the caller to check.
+///
+/// # Errors
+///
+/// Fails when a query fails.
+pub async fn authenticate_token(
+    pool: &PgPool,
+    token: &str,
+) -> anyhow::Result<Option<(User, ApiToken)>> {
+    if !token.starts_with(TOKEN_PREFIX) {
+        return Ok(None);
+    }
+    let filter = Filter::new().eq("token_hash", hash_token(token)).limit(1);
+    let record: Option<ApiToken> = pool.find_many(filter).await?.pop();
+    let now = Utc::now();
+    let Some(mut record) = record.filter(|record| is_live(record, now)) else {
+        return Ok(None);
+    };
+    let Some(user) = pool.find_by_id(&record.user_id).await? else {
+        return Ok(None);
+    };
+
+    if needs_touch(record.last_used_at, now) {
+        sqlx::query("UPDATE api_tokens SET last_used_at = $2 WHERE id = $1")
+            .bind(record.id)
+            .bind(now)
+            .execute(pool)
+            .await?;
+        record.last_used_at = Some(now);
+    }
+    Ok(Some((user, record)))
+}
+
+fn generate_token() -> String {
+    let random = Alphanumeric.sample_string(&mut rand::thread_rng(), TOKEN_LEN);
+    format!("{TOKEN_PREFIX}{random}")
+}
+
+/// Tokens are random enough that a fast hash cannot be brute forced, and
+/// it lets them be looked up by hash.
+fn hash_token(token: &str) -> Vec<u8> {
+    Sha256::digest(token.as_bytes()).to_vec()
+}
+
+fn is_live(record: &ApiToken, now: DateTime<Utc>) -> bool {
+    record.expires_at.is_none_or(|expires_at| expires_at > now)
+}
+
+fn needs_touch(
+    last_used_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> bool {
+    last_used_at.is_none_or(|last_used_at| now - last_used_at >= TOUCH_INTERVAL)
+}
+
+#[test]
+fn test() {
+    let token = generate_token();
+    assert!(token.starts_with(TOKEN_PREFIX));
+    assert_eq!(token.len(), TOKEN_PREFIX.len() + TOKEN_LEN);
+    assert_ne!(token, generate_token());
+    assert_eq!(hash_token(&token), hash_token(&token));
+    assert_eq!(hash_token(&token).len(), 32);
+
+    let now = Utc::now();
+    let record = |expires_at| ApiToken {
+        id: 1,
+        user_id: 1,
+        name: "ci".to_string(),
+        token_hash: Vec::new(),
+        scopes: Vec::new(),
+        created_at: now,
+        last_used_at: None,
+        expires_at,
+    };
+    assert!(is_live(&record(None), now));
+    assert!(is_live(&record(Some(now + TimeDelta::hours(1))), now));
+    assert!(!is_live(&record(Some(now)), now));
+
+    // A user without a permission cannot give it to a token
+    let granted = HashSet::from(["things.read".to_string()]);
+    let scopes = |names: &[&str]| -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    };
+    assert_eq!(check_scopes(&scopes(&["things.read"]), &granted), Ok(()));
+    assert_eq!(check_scopes(&[], &HashSet::new()), Ok(()));
+    assert_eq!(
+        check_scopes(&scopes(&["things.read", "things.write"]), &granted),
+        Err(ScopeError::NotGranted("things.write".to_string()))
+    );
+    assert_eq!(
+        check_scopes(&scopes(&["things.read"]), &HashSet::new()),
+        Err(ScopeError::NotGranted("things.read".to_string()))
+    );
+    assert_eq!(
+        check_scopes(&["x".repeat(MAX_SCOPE_LEN + 1)], &granted),
+        Err(ScopeError::TooLong)
+    );
+
+    assert!(needs_touch(None, now));
+    assert!(!needs_touch(Some(now - TimeDelta::seconds(10)), now));
+    assert!(needs_touch(Some(now - TimeDelta::minutes(5)), now));
+}
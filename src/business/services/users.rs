@@ -0,0 +1,80 @@
+use repositories::{Filter, Loadable, Repository};
+use sqlx::PgConnection;
+use tracing::{event, Level};
+use types::entities::User;
+
+use crate::{
+    hash_password, verify_dummy_password, verify_password, PasswordCheck,
+};
+
+/// Emails are stored and compared trimmed and lowercase.
+#[must_use]
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Creates an account, or returns `None` when the email is already taken,
+/// concurrent registrations included.
+///
+/// # Errors
+///
+/// Fails when the query fails.
+pub async fn register(
+    conn: &mut PgConnection,
+    email: &str,
+    password: String,
+) -> Loadable<User> {
+    // Hashed even when the email is taken, which takes as long either way
+    let password_hash = hash_password(password).await?;
+    let user = sqlx::query_as(
+        "INSERT INTO users (email, password_hash) VALUES ($1, $2) \
+         ON CONFLICT (email) DO NOTHING \
+         RETURNING id, email, password_hash, created_at",
+    )
+    .bind(normalize_email(email))
+    .bind(password_hash)
+    .fetch_optional(conn)
+    .await?;
+    Ok(user)
+}
+
+/// The user with these credentials, or `None` when the email or the
+/// password is wrong. A hash made with outdated parameters is replaced.
+///
+/// # Errors
+///
+/// Fails when a query fails, or when the stored hash is invalid.
+pub async fn authenticate(
+    conn: &mut PgConnection,
+    email: &str,
+    password: String,
+) -> Loadable<User> {
+    let Some(mut user) = find_by_email(conn, &normalize_email(email)).await?
+    else {
+        verify_dummy_password(password).await?;
+        return Ok(None);
+    };
+
+    let hash = user.password_hash.clone();
+    let check = verify_password(password.clone(), hash).await?;
+    match check {
+        PasswordCheck::Mismatch => Ok(None),
+        PasswordCheck::Match { rehash: false } => Ok(Some(user)),
+        PasswordCheck::Match { rehash: true } => {
+            user.password_hash = hash_password(password).await?;
+            let updated = conn.update(&user).await?;
+            event!(Level::INFO, "Rehashed the password of user {}", user.id);
+            Ok(updated)
+        }
+    }
+}
+
+async fn find_by_email(conn: &mut PgConnection, email: &str) -> Loadable<User> {
+    let filter = Filter::new().eq("email", email.to_string()).limit(1);
+    Ok(conn.find_many(filter).await?.pop())
+}
+
+#[test]
+fn test() {
+    assert_eq!(normalize_email("  Ada@Example.COM "), "ada@example.com");
+}
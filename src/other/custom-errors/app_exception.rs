@@ -1,14 +1,28 @@
 use std::fmt;
 
 use axum::http::StatusCode;
+use lazy_static::lazy_static;
+use prometheus::{register_int_counter, IntCounter};
 use tracing::{event, Level};
 use uuid::Uuid;
 
 use super::err_response::ErrResponse;
 
+lazy_static! {
+    static ref APP_EXCEPTIONS: IntCounter = register_int_counter!(
+        "app_exceptions_total",
+        "Internal server errors, each logged with an identifier"
+    )
+    .unwrap();
+}
+
 #[derive(Debug)]
 pub struct AppException {
     identifier: Uuid,
+    /// Trace of the request that failed, when there is one.
+    trace_id: Option<String>,
+    /// `X-Request-Id` of the request that failed, when there is one.
+    request_id: Option<String>,
     pub source: anyhow::Error,
 }
 
@@ -16,10 +30,20 @@ impl AppException {
     pub fn new(source: anyhow::Error) -> Self {
         let result = Self {
             identifier: Uuid::new_v4(),
+            trace_id: utils::current_trace_id(),
+            request_id: utils::current_request_id(),
             source,
         };
 
-        event!(Level::ERROR, "{result}");
+        // Separate fields, so that they are keys of the JSON logs
+        event!(
+            Level::ERROR,
+            identifier = %result.identifier,
+            request_id = result.request_id(),
+            trace_id = result.trace_id(),
+            "{result}"
+        );
+        APP_EXCEPTIONS.inc();
 
         result
     }
@@ -28,6 +52,16 @@ impl AppException {
     pub const fn identifier(&self) -> Uuid {
         self.identifier
     }
+
+    #[must_use]
+    pub fn trace_id(&self) -> Option<&str> {
+        self.trace_id.as_deref()
+    }
+
+    #[must_use]
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
 }
 
 fn cut_trace(trace: &str) -> &str {
@@ -42,8 +42,13 @@ impl IntoResponse for AppRejection<HtmlKind> {
             status_code,
             ..
         } = self;
-        let html =
-            Html(error::render(status_code.as_u16(), message, None, false));
+        let html = Html(error::render(
+            status_code.as_u16(),
+            message,
+            None,
+            utils::current_request_id(),
+            false,
+        ));
         (status_code, html).into_response()
     }
 }
@@ -56,7 +61,8 @@ impl IntoResponse for AppRejection<JsonKind> {
             status_code,
             ..
         } = self;
-        let api_response = api::Response::<()>::error(message, None);
+        let api_response = api::Response::<()>::error(message, None)
+            .with_request_id(utils::current_request_id());
         (status_code, Json(api_response)).into_response()
     }
 }
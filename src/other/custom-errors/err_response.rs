@@ -1,7 +1,7 @@
 use axum::{
     http::StatusCode,
     response::{Html, IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
 use std::marker::PhantomData;
 use types::api;
@@ -33,6 +33,11 @@ impl<Kind> ErrResponse<Kind> {
 pub struct HtmlKind;
 pub struct JsonKind;
 
+/// Marks responses built from an `ErrResponse`, so that layers can tell them
+/// apart from successful ones (e.g. to roll back the request transaction).
+#[derive(Debug, Clone, Copy)]
+pub struct Failed;
+
 // Tell axum how to convert `ErrResponse<HtmlKind>` into a response.
 impl IntoResponse for ErrResponse<HtmlKind> {
     fn into_response(self) -> Response {
@@ -46,9 +51,10 @@ impl IntoResponse for ErrResponse<HtmlKind> {
             status_code.as_u16(),
             message,
             identifier.map(|uuid| uuid.to_string()),
+            utils::current_request_id(),
             true,
         ));
-        (status_code, html).into_response()
+        (status_code, Extension(Failed), html).into_response()
     }
 }
 
@@ -61,8 +67,9 @@ impl IntoResponse for ErrResponse<JsonKind> {
             identifier,
             ..
         } = self;
-        let api_response = api::Response::<()>::error(message, identifier);
-        (status_code, Json(api_response)).into_response()
+        let api_response = api::Response::<()>::error(message, identifier)
+            .with_request_id(utils::current_request_id());
+        (status_code, Extension(Failed), Json(api_response)).into_response()
     }
 }
 
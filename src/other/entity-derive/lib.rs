@@ -0,0 +1,183 @@
+//! `#[derive(Entity)]`, implementing `types::entities::Entity` for a struct
+//! mapped to a table. See the trait for the attributes.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr, Type};
+
+struct Column {
+    ident: Ident,
+    ty: Type,
+    id: bool,
+    generated: bool,
+}
+
+#[proc_macro_derive(Entity, attributes(entity))]
+pub fn derive_entity(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let mut table = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("entity")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("table") {
+                table = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("unknown entity attribute, expected `table`"))
+            }
+        })?;
+    }
+    let table = table.unwrap_or_else(|| snake_case(&input.ident.to_string()));
+
+    let columns = columns(input)?;
+    let id = {
+        let mut ids = columns.iter().filter(|c| c.id);
+        match (ids.next(), ids.next()) {
+            (Some(id), None) => id,
+            (None, _) => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "an entity needs an `id` field, or one marked \
+                     `#[entity(id)]`",
+                ))
+            }
+            (Some(_), Some(other)) => {
+                return Err(syn::Error::new_spanned(
+                    &other.ident,
+                    "an entity can only have one id",
+                ))
+            }
+        }
+    };
+
+    let name = |c: &&Column| c.ident.to_string();
+    let all: Vec<String> = columns.iter().map(|c| name(&c)).collect();
+    let inserted: Vec<&Column> =
+        columns.iter().filter(|c| !c.generated).collect();
+    let updated: Vec<&Column> =
+        inserted.iter().copied().filter(|c| !c.id).collect();
+    let inserted_names: Vec<String> = inserted.iter().map(name).collect();
+    let updated_names: Vec<String> = updated.iter().map(name).collect();
+    let inserted_idents = inserted.iter().map(|c| &c.ident);
+    let updated_idents = updated.iter().map(|c| &c.ident);
+
+    let ident = &input.ident;
+    let id_ident = &id.ident;
+    let id_name = id.ident.to_string();
+    let id_ty = &id.ty;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::types::entities::Entity
+            for #ident #ty_generics #where_clause
+        {
+            type Id = #id_ty;
+
+            const TABLE: &'static str = #table;
+            const ID: &'static str = #id_name;
+            const COLUMNS: &'static [&'static str] = &[#(#all),*];
+            const INSERT_COLUMNS: &'static [&'static str] =
+                &[#(#inserted_names),*];
+            const UPDATE_COLUMNS: &'static [&'static str] =
+                &[#(#updated_names),*];
+
+            fn id(&self) -> &Self::Id {
+                &self.#id_ident
+            }
+
+            fn bind_insert<'q>(
+                &'q self,
+                query: ::types::entities::EntityQuery<'q, Self>,
+            ) -> ::types::entities::EntityQuery<'q, Self> {
+                query #(.bind(&self.#inserted_idents))*
+            }
+
+            fn bind_update<'q>(
+                &'q self,
+                query: ::types::entities::EntityQuery<'q, Self>,
+            ) -> ::types::entities::EntityQuery<'q, Self> {
+                query #(.bind(&self.#updated_idents))*
+            }
+        }
+    })
+}
+
+fn columns(input: &DeriveInput) -> syn::Result<Vec<Column>> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "Entity can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "Entity can only be derived for structs with named fields",
+        ));
+    };
+
+    let mut columns = Vec::new();
+    for field in &fields.named {
+        let ident = field.ident.clone().expect("Named fields have idents");
+        let mut column = Column {
+            ident,
+            id: false,
+            ty: field.ty.clone(),
+            generated: false,
+        };
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("entity")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("id") {
+                    column.id = true;
+                    Ok(())
+                } else if meta.path.is_ident("generated") {
+                    column.generated = true;
+                    Ok(())
+                } else {
+                    Err(meta.error(
+                        "unknown entity attribute, expected `id` or \
+                         `generated`",
+                    ))
+                }
+            })?;
+        }
+        columns.push(column);
+    }
+
+    // Without an explicit id, the `id` field is the one
+    if !columns.iter().any(|c| c.id) {
+        if let Some(column) = columns.iter_mut().find(|c| c.ident == "id") {
+            column.id = true;
+        }
+    }
+    Ok(columns)
+}
+
+/// `UserRole` → `user_role`.
+fn snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[test]
+fn test() {
+    assert_eq!(snake_case("User"), "user");
+    assert_eq!(snake_case("UserRole"), "user_role");
+}
@@ -1,42 +1,58 @@
-//! In this file, we define the environment variables that are used multiple times
-//! in the program, and therefore are better left as static variables.
-//!
-//! There are other environment variables that are only used once:
-//! - `LOG_SEVERITY` - Severity level for the log file and stdout.
-//!   * Defaults to `INFO`. Used at `utils::init_logging`.
-//! - `LOG_DIRECTORY` - The directory where the log files are stored.
-//!   * Defaults to `/var/log/LunarParfait/conecta`. Used at `utils::init_logging`.
-//! - `DATABASE_URL` - The URL to the database.
-//!   * Does not have a default. Program will panic if undefined. Used at `Database::init`.
-//! - `DB_CONN_POOL_MAX` - The maximum number of connections to the database.
-//!   * Defaults to `100`. Used at `Database::init`.
+//! In this file, we define the static environment of the program: the
+//! workspace directory and the typed configuration (see `AppConfig`), which
+//! is merged from every layer (see `Sources`), then loaded and validated
+//! once, at boot.
 
 use anyhow::anyhow;
-use std::{
-    net::{IpAddr, Ipv4Addr},
-    path::Path,
-};
+use std::path::Path;
 
-use crate::{owned_var_or, try_leak, var_or, EnvLock};
+use crate::{
+    try_leak, AppConfig, ConfigError, EnvLock, Layer, Options, Resolved,
+    Sources,
+};
 
 pub struct Environment {
-    pub hostname: IpAddr,
-    pub port: u16,
-    pub domain: &'static str,
     pub workspace_dir: &'static Path,
+    pub profile: Option<String>,
+    pub config: AppConfig,
+    /// The effective raw value of every setting, and the layer it came from.
+    pub resolved: Vec<Resolved>,
+    /// The layers the configuration was loaded from, see `reload`.
+    options: Options,
 }
 
 impl Environment {
-    /// # Panics
-    /// Will panic if it fails to parse the environment variables
+    /// # Errors
+    /// Will error with every problem found if the configuration is invalid.
+    pub fn load(options: &Options) -> Result<Self, ConfigError> {
+        let sources = Sources::load(options)?;
+        let (config, resolved) = AppConfig::load(&sources)?;
+        Ok(Self {
+            workspace_dir: options.workspace_dir,
+            profile: sources.profile(options),
+            config,
+            resolved,
+            options: options.clone(),
+        })
+    }
+
+    /// Reads and validates every layer again, for the few settings that can
+    /// change at runtime (e.g. the log filter). `ENV` itself is not changed.
+    ///
+    /// # Errors
+    /// Will error with every problem found if the configuration is invalid.
+    pub fn reload(&self) -> Result<AppConfig, ConfigError> {
+        let sources = Sources::load(&self.options)?;
+        Ok(AppConfig::load(&sources)?.0)
+    }
+
+    /// Which layer the effective value of `key` came from.
     #[must_use]
-    pub fn new(workspace_dir: &'static Path) -> Self {
-        Self {
-            hostname: owned_var_or("HOSTNAME", IpAddr::V4(Ipv4Addr::LOCALHOST)),
-            port: owned_var_or("PORT", 3000),
-            domain: var_or::<String, _>("DOMAIN", "localhost"),
-            workspace_dir,
-        }
+    pub fn origin(&self, key: &str) -> Option<&Layer> {
+        self.resolved
+            .iter()
+            .find(|r| r.key == key)
+            .map(|r| &r.layer)
     }
 }
 
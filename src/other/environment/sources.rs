@@ -0,0 +1,305 @@
+//! The layers the configuration is merged from, from lowest to highest
+//! precedence:
+//! 1. Built-in defaults, declared in `AppConfig::load`.
+//! 2. `config/default.toml`, checked in.
+//! 3. `config/<profile>.toml`, where the profile comes from `--profile`, or
+//!    from the `APP_ENV` variable (environment or `.env`).
+//! 4. `.env`, at the workspace root.
+//! 5. The process environment variables.
+//! 6. Command-line overrides (`--set <key>=<value>`).
+//!
+//! Configuration files and command-line overrides use dotted keys (e.g.
+//! `server.port`), while `.env` and the environment use the variable names
+//! (e.g. `PORT`).
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::{owned_var_opt, ConfigError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layer {
+    Default,
+    File(PathBuf),
+    DotEnv(PathBuf),
+    Env,
+    Cli,
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "built-in default"),
+            Self::File(path) | Self::DotEnv(path) => {
+                write!(f, "{}", path.display())
+            }
+            Self::Env => write!(f, "environment"),
+            Self::Cli => write!(f, "command line"),
+        }
+    }
+}
+
+/// What is needed to find every configuration layer.
+#[derive(Clone)]
+pub struct Options {
+    pub workspace_dir: &'static Path,
+    /// Selects `config/<profile>.toml`. Falls back to `APP_ENV`.
+    pub profile: Option<String>,
+    /// Raw `<key>=<value>` overrides, as given on the command line.
+    pub overrides: Vec<String>,
+}
+
+impl Options {
+    #[must_use]
+    pub const fn new(workspace_dir: &'static Path) -> Self {
+        Self {
+            workspace_dir,
+            profile: None,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Sources {
+    /// In increasing order of precedence.
+    files: Vec<(PathBuf, BTreeMap<String, String>)>,
+    dotenv: Option<(PathBuf, BTreeMap<String, String>)>,
+    read_env: bool,
+    overrides: BTreeMap<String, String>,
+}
+
+impl Sources {
+    /// Reads every layer described by `options`.
+    ///
+    /// # Errors
+    /// Returns every unreadable file, invalid override, or missing profile
+    /// overlay at once.
+    pub fn load(options: &Options) -> Result<Self, ConfigError> {
+        let mut errors = Vec::new();
+        let mut sources = Self {
+            read_env: true,
+            ..Self::default()
+        };
+
+        let dotenv_path = options.workspace_dir.join(".env");
+        if dotenv_path.exists() {
+            match read_dotenv(&dotenv_path) {
+                Ok(vars) => sources.dotenv = Some((dotenv_path, vars)),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        let config_dir = options.workspace_dir.join("config");
+        let default_path = config_dir.join("default.toml");
+        if default_path.exists() {
+            if let Err(e) = sources.push_file(&default_path) {
+                errors.push(e);
+            }
+        }
+
+        if let Some(profile) = sources.profile(options) {
+            let profile_path = config_dir.join(format!("{profile}.toml"));
+            if profile_path.exists() {
+                if let Err(e) = sources.push_file(&profile_path) {
+                    errors.push(e);
+                }
+            } else {
+                errors.push(format!(
+                    "profile `{profile}` has no configuration file at {}",
+                    profile_path.display()
+                ));
+            }
+        }
+
+        for raw in &options.overrides {
+            if let Err(e) = sources.push_override(raw) {
+                errors.push(e);
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(sources)
+        } else {
+            Err(ConfigError { errors })
+        }
+    }
+
+    /// Finds the value of a setting in the highest precedence layer that
+    /// defines it. Empty values count as unset.
+    ///
+    /// # Errors
+    /// When the environment variable is not valid unicode.
+    pub fn get(
+        &self,
+        key: &str,
+        env: &'static str,
+    ) -> Result<Option<(String, Layer)>, anyhow::Error> {
+        if let Some(value) = self.overrides.get(key) {
+            return Ok(Some((value.clone(), Layer::Cli)));
+        }
+        if self.read_env {
+            if let Some(value) = owned_var_opt::<String>(env)? {
+                return Ok(Some((value, Layer::Env)));
+            }
+        }
+        if let Some((path, vars)) = &self.dotenv {
+            if let Some(value) = vars.get(env).filter(|v| !v.is_empty()) {
+                return Ok(Some((value.clone(), Layer::DotEnv(path.clone()))));
+            }
+        }
+        Ok(self.files.iter().rev().find_map(|(path, values)| {
+            let value = values.get(key).filter(|v| !v.is_empty())?;
+            Some((value.clone(), Layer::File(path.clone())))
+        }))
+    }
+
+    /// Every dotted key defined by a configuration file or an override.
+    pub fn keys(&self) -> impl Iterator<Item = (&str, Layer)> {
+        let files = self.files.iter().flat_map(|(path, values)| {
+            values
+                .keys()
+                .map(|k| (k.as_str(), Layer::File(path.clone())))
+        });
+        let overrides = self.overrides.keys().map(|k| (k.as_str(), Layer::Cli));
+        files.chain(overrides)
+    }
+
+    /// The profile overlay to apply, if any.
+    #[must_use]
+    pub fn profile(&self, options: &Options) -> Option<String> {
+        let from_dotenv = || {
+            let (_, vars) = self.dotenv.as_ref()?;
+            vars.get("APP_ENV").filter(|v| !v.is_empty()).cloned()
+        };
+        options
+            .profile
+            .clone()
+            .or_else(|| owned_var_opt("APP_ENV").ok().flatten())
+            .or_else(from_dotenv)
+    }
+
+    fn push_file(&mut self, path: &Path) -> Result<(), String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        let values = parse_toml(&contents)
+            .map_err(|e| format!("failed to parse {}: {e}", path.display()))?;
+        self.files.push((path.to_path_buf(), values));
+        Ok(())
+    }
+
+    fn push_override(&mut self, raw: &str) -> Result<(), String> {
+        let (key, value) = raw.split_once('=').ok_or_else(|| {
+            format!("override `{raw}` is not in the `<key>=<value>` form")
+        })?;
+        self.overrides
+            .insert(key.trim().to_string(), value.trim().to_string());
+        Ok(())
+    }
+}
+
+fn read_dotenv(path: &Path) -> Result<BTreeMap<String, String>, String> {
+    let fail =
+        |e: dotenvy::Error| format!("failed to read {}: {e}", path.display());
+    dotenvy::from_path_iter(path)
+        .map_err(fail)?
+        .map(|item| item.map_err(fail))
+        .collect()
+}
+
+/// Flattens a TOML document into dotted keys. Arrays are joined by commas.
+fn parse_toml(
+    contents: &str,
+) -> Result<BTreeMap<String, String>, toml::de::Error> {
+    fn flatten(
+        prefix: &str,
+        table: &toml::Table,
+        out: &mut BTreeMap<String, String>,
+    ) {
+        for (name, value) in table {
+            let key = if prefix.is_empty() {
+                name.clone()
+            } else {
+                format!("{prefix}.{name}")
+            };
+            match value {
+                toml::Value::Table(inner) => flatten(&key, inner, out),
+                toml::Value::Array(items) => {
+                    let joined: Vec<String> =
+                        items.iter().map(scalar).collect();
+                    out.insert(key, joined.join(","));
+                }
+                other => {
+                    out.insert(key, scalar(other));
+                }
+            }
+        }
+    }
+    fn scalar(value: &toml::Value) -> String {
+        match value {
+            toml::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }
+    }
+
+    let mut out = BTreeMap::new();
+    flatten("", &contents.parse::<toml::Table>()?, &mut out);
+    Ok(out)
+}
+
+/// Builds sources from in-memory TOML documents and overrides, ignoring the
+/// process environment.
+///
+/// # Panics
+/// When a document or an override is invalid.
+#[cfg(test)]
+#[must_use]
+pub fn in_memory(files: &[&str], overrides: &[&str]) -> Sources {
+    let mut sources = Sources::default();
+    for (i, contents) in files.iter().enumerate() {
+        let values = parse_toml(contents).unwrap();
+        sources
+            .files
+            .push((PathBuf::from(format!("{i}.toml")), values));
+    }
+    for raw in overrides {
+        sources.push_override(raw).unwrap();
+    }
+    sources
+}
+
+#[cfg(test)]
+impl Sources {
+    /// Adds a `.env` layer of `(variable, value)` pairs to in-memory sources.
+    #[must_use]
+    pub fn with_dotenv(mut self, vars: &[(&str, &str)]) -> Self {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+            .collect();
+        self.dotenv = Some((PathBuf::from(".env"), vars));
+        self
+    }
+}
+
+#[test]
+fn test() {
+    let sources = in_memory(
+        &[
+            "[server]\nport = 3000\nhostname = \"0.0.0.0\"",
+            "[server]\nport = 8080",
+        ],
+        &["server.hostname=::1"],
+    );
+    let (port, layer) = sources.get("server.port", "").unwrap().unwrap();
+    assert_eq!(
+        (port.as_str(), layer),
+        ("8080", Layer::File("1.toml".into()))
+    );
+    let (host, layer) = sources.get("server.hostname", "").unwrap().unwrap();
+    assert_eq!((host.as_str(), layer), ("::1", Layer::Cli));
+    assert!(sources.get("server.domain", "").unwrap().is_none());
+}
@@ -9,7 +9,7 @@ pub fn canonicalize_unexistent(s: &Path) -> Option<PathBuf> {
             Ok::<PathBuf, anyhow::Error>(canonical.join(stripped))
         })() {
             return Some(path);
-        };
+        }
     }
     None
 }
@@ -1,96 +1,179 @@
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 use debug_print::debug_println;
-use tracing::level_filters::LevelFilter;
+use rolling_file::{BasicRollingFileAppender, RollingConditionBasic};
+use tracing::Subscriber;
 use tracing_appender::non_blocking::WorkerGuard;
-use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Layer};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{layer::SubscriberExt, reload, EnvFilter, Layer};
 
-use environment::owned_var_or_else;
+use environment::{LogFormat, LogRotation, LoggingConfig, ENV};
+
+use crate::LogFilter;
 
 /// This method initializes the logging system for the application.
-/// It reads the following environment variables:
-/// - `LOG_DIRECTORY` - The directory where the logs will be stored. Defaults to `/var/log/conecta`.
-/// - `LOG_SEVERITY` - The minimum severity level for logs. Defaults to `INFO`.
+/// It reads the `logging` section of the configuration:
+/// - `directory` - The directory where the logs will be stored.
+/// - `severity` - The minimum severity level for logs.
+/// - `directives` - Extra `EnvFilter` directives, in the `RUST_LOG` format.
+/// - `stdout_format` / `file_format` - How each output is written.
+/// - `file_enabled`, `rotation`, `max_files`... - How log files are written.
 ///
-/// The logs are written to the console and to a file in the specified directory.
+/// The logs are written to the console and, unless disabled, to rotating
+/// files in the specified directory.
+/// Spans are also recorded with OpenTelemetry, see `otel_layer`.
 ///
 /// # Returns
-/// Two `WorkerGuards` that need to live the entire lifetime of the application.
+/// The `WorkerGuards` of the file, if any, and of stdout. They need to live
+/// the entire lifetime of the application.
 ///
 /// # Panics
 ///
 /// When logging fails to initialize.
-pub async fn init_logging() -> (WorkerGuard, WorkerGuard) {
+pub async fn init_logging() -> (Option<WorkerGuard>, WorkerGuard) {
     debug_println!("\nInitializing logging...\n");
 
     // Initializing color_eyre for better error handling
     color_eyre::install().unwrap_or_default();
 
-    // Ensuring the log directory exists
-    let log_dir = log_directory().await;
-    // Reading the log severity level
-    let log_severity: String =
-        owned_var_or_else("LOG_SEVERITY", || String::from("INFO"));
+    let config = &ENV.config.logging;
 
-    // Filtering crates
-    let filtered = vec![]; // You can had here any crates that are too verbose
-    let env_filter = filter(&filtered, &log_severity);
+    // Filtering crates, reloadable through `set_log_filter`
+    let log_filter = LogFilter {
+        severity: config.severity,
+        directives: config.directives.clone(),
+    };
+    let (env_filter, fmt_handle) = reload::Layer::new(filter(&log_filter));
 
     // Setting up the file and stdout appenders
-    let file_appender =
-        tracing_appender::rolling::daily(log_dir, "Conecta.log");
-    let (non_blocking_file, guard0) =
-        tracing_appender::non_blocking(file_appender);
+    let (file_log, guard0) = if config.file_enabled {
+        // Ensuring the log directory exists
+        let log_dir = log_directory(&config.directory).await;
+        let (non_blocking_file, guard) =
+            tracing_appender::non_blocking(file_appender(config, &log_dir));
+        let layer = fmt_layer(config.file_format, non_blocking_file);
+        (Some(layer), Some(guard))
+    } else {
+        (None, None)
+    };
     let (non_blocking_stdout, guard1) =
         tracing_appender::non_blocking(std::io::stdout());
 
-    let file_log = tracing_subscriber::fmt::layer()
-        .compact()
-        .with_writer(non_blocking_file);
-    let stdout_log = tracing_subscriber::fmt::layer()
-        .pretty()
-        .with_writer(non_blocking_stdout);
+    let stdout_log = fmt_layer(config.stdout_format, non_blocking_stdout);
 
     let layered = stdout_log.and_then(file_log).with_filter(env_filter);
 
-    // Setting up the subscriber with the stdout/file layer
-    let subscriber = tracing_subscriber::registry().with(layered);
+    // Same severity for traces, so that they match the logs
+    let (otel_filter, otel_handle) = reload::Layer::new(filter(&log_filter));
+    let otel_log = super::otel_layer().with_filter(otel_filter);
+
+    // Setting up the subscriber with the stdout/file and OpenTelemetry layers
+    let subscriber =
+        tracing_subscriber::registry().with(layered).with(otel_log);
     tracing::subscriber::set_global_default(subscriber).unwrap();
 
+    super::install(
+        log_filter,
+        Box::new(move |log_filter| {
+            fmt_handle.reload(log_filter.env_filter()?)?;
+            otel_handle.reload(log_filter.env_filter()?)?;
+            Ok(())
+        }),
+    );
+
     debug_println!("Success!\n");
     debug_println!("-------------------------------------------------------\n");
     (guard0, guard1)
 }
 
-// This function creates the log directory and returns its path.
-async fn log_directory() -> PathBuf {
-    let log_dir = owned_var_or_else("LOG_DIRECTORY", || {
-        PathBuf::from("/var/log/LunarParfait/conecta")
-    });
+// This function creates the rotating log file writer.
+fn file_appender(
+    config: &LoggingConfig,
+    log_dir: &Path,
+) -> Box<dyn Write + Send> {
+    let rotation = match config.rotation {
+        LogRotation::Minutely => Rotation::MINUTELY,
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Never => Rotation::NEVER,
+        LogRotation::Size => {
+            // Rotated files get a `.1`, `.2`... extension, `.1` being newest
+            let name: Vec<&str> = [&config.file_prefix, &config.file_suffix]
+                .into_iter()
+                .map(String::as_str)
+                .filter(|part| !part.is_empty())
+                .collect();
+            let condition =
+                RollingConditionBasic::new().max_size(config.max_file_size);
+            let appender = BasicRollingFileAppender::new(
+                log_dir.join(name.join(".")),
+                condition,
+                config.max_files,
+            )
+            .unwrap_or_else(|e| panic!("Failed to open log file: {e}"));
+            return Box::new(appender);
+        }
+    };
+
+    let mut builder = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(&config.file_prefix)
+        .filename_suffix(&config.file_suffix);
+    if config.max_files > 0 {
+        // The current file counts as well here
+        builder = builder.max_log_files(config.max_files + 1);
+    }
+    let appender = builder
+        .build(log_dir)
+        .unwrap_or_else(|e| panic!("Failed to open log file: {e}"));
+    Box::new(appender)
+}
+
+// This function creates the formatting layer of one output.
+fn fmt_layer<S, W>(
+    format: LogFormat,
+    writer: W,
+) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Compact => layer.compact().boxed(),
+        // Event fields at the top level, span fields under `spans`
+        LogFormat::Json => layer
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .boxed(),
+    }
+}
 
-    let canonical = super::canonicalize_unexistent(&log_dir)
+// This function creates the log directory and returns its path.
+async fn log_directory(log_dir: &Path) -> PathBuf {
+    let canonical = super::canonicalize_unexistent(log_dir)
         .unwrap_or_else(|| panic!("Failed to canonicalize path!"));
 
     tokio::fs::create_dir_all(&canonical)
-      .await
-      .unwrap_or_else(|e| panic!("Failed to create canonical directory: {e}. Path: {canonical:?}"));
+        .await
+        .unwrap_or_else(|e| {
+            panic!(
+                "Failed to create canonical directory: {e}. Path: {}",
+                canonical.display()
+            )
+        });
 
     canonical
 }
 // This function creates the filter for the logging system.
-fn filter(filter_entries: &[&str], log_severity: &str) -> EnvFilter {
+fn filter(log_filter: &LogFilter) -> EnvFilter {
     debug_println!("Defining EnvFilter...\n");
-    #[expect(clippy::unwrap_used)] // Safe because it's a constant
-    let filter = EnvFilter::builder()
-        .with_default_directive(
-            log_severity.parse::<LevelFilter>().unwrap().into(),
-        )
-        .from_env()
-        .unwrap_or_else(|e| {
-            panic!("Invalid directives for tracing subscriber: {e}.")
-        });
-
-    filter_entries.iter().fold(filter, |acc, s| {
-        acc.add_directive(format!("{s}=warn").parse().unwrap())
+    log_filter.env_filter().unwrap_or_else(|e| {
+        panic!("Invalid directives for tracing subscriber: {e}.")
     })
 }
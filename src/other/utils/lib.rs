@@ -3,3 +3,15 @@ pub use canonicalize_unexistent::*;
 
 mod init_logging;
 pub use init_logging::*;
+
+mod log_filter;
+pub use log_filter::*;
+
+mod shutdown_hooks;
+pub use shutdown_hooks::*;
+
+mod telemetry;
+pub use telemetry::*;
+
+mod request_id;
+pub use request_id::*;
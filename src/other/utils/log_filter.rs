@@ -0,0 +1,117 @@
+//! The log filter, changeable at runtime without restarting.
+
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::{Directive, ParseError};
+use tracing_subscriber::EnvFilter;
+
+/// Crates that are too verbose, only logged from `WARN` on.
+const FILTERED: &[&str] = &[];
+
+/// Swaps the filter of every layer, see `init_logging`.
+pub type Reload = Box<dyn Fn(&LogFilter) -> anyhow::Result<()> + Send + Sync>;
+
+lazy_static! {
+    static ref CURRENT: Mutex<Option<(LogFilter, Reload)>> = Mutex::new(None);
+}
+
+/// What is logged, as set by `LOG_SEVERITY` and `RUST_LOG`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFilter {
+    /// Level of everything no directive matches.
+    pub severity: LevelFilter,
+    /// `EnvFilter` directives, in the `RUST_LOG` format.
+    pub directives: String,
+}
+
+impl LogFilter {
+    /// Builds the `EnvFilter` of one layer.
+    ///
+    /// # Errors
+    ///
+    /// When the directives are invalid.
+    pub fn env_filter(&self) -> Result<EnvFilter, ParseError> {
+        // Added one by one, as `with_default_directive` is dropped as soon
+        // as there is any directive
+        let filter = EnvFilter::default().add_directive(self.severity.into());
+        let filter = self
+            .directives
+            .split(',')
+            .map(str::trim)
+            .filter(|directive| !directive.is_empty())
+            .try_fold(filter, |acc, directive| {
+                directive.parse().map(|d: Directive| acc.add_directive(d))
+            })?;
+
+        FILTERED.iter().try_fold(filter, |acc, s| {
+            Ok(acc.add_directive(format!("{s}=warn").parse()?))
+        })
+    }
+}
+
+/// Registers the filter logging started with, and how to replace it. Done by
+/// `init_logging`, only needed with another subscriber.
+///
+/// # Panics
+///
+/// Panics when another thread panicked while changing the filter.
+pub fn install(filter: LogFilter, reload: Reload) {
+    *CURRENT.lock().unwrap() = Some((filter, reload));
+}
+
+/// The active log filter, `None` before logging is initialized.
+///
+/// # Panics
+///
+/// Panics when another thread panicked while changing the filter.
+#[must_use]
+pub fn log_filter() -> Option<LogFilter> {
+    let current = CURRENT.lock().unwrap();
+    current.as_ref().map(|(filter, _)| filter.clone())
+}
+
+/// Replaces the log filter of every layer.
+///
+/// # Errors
+///
+/// When the directives are invalid, or logging is not initialized. The
+/// active filter is kept then.
+///
+/// # Panics
+///
+/// Panics when another thread panicked while changing the filter.
+pub fn set_log_filter(filter: LogFilter) -> anyhow::Result<()> {
+    let mut current = CURRENT.lock().unwrap();
+    let Some((active, reload)) = current.as_mut() else {
+        anyhow::bail!("Logging is not initialized");
+    };
+    // Parsed before anything is swapped, so that it is all or nothing
+    filter.env_filter()?;
+    reload(&filter)?;
+    *active = filter;
+    drop(current);
+    Ok(())
+}
+
+#[test]
+fn test() {
+    let filter = LogFilter {
+        severity: LevelFilter::INFO,
+        directives: "sqlx=debug".to_string(),
+    };
+    assert_eq!(filter.env_filter().unwrap().to_string(), "sqlx=debug,info");
+    let bare_level = LogFilter {
+        directives: "warn".to_string(),
+        ..filter
+    };
+    assert_eq!(bare_level.env_filter().unwrap().to_string(), "warn");
+
+    let invalid = LogFilter {
+        directives: "sqlx=loud".to_string(),
+        ..filter
+    };
+    assert!(invalid.env_filter().is_err());
+    assert!(set_log_filter(invalid).is_err());
+}
@@ -0,0 +1,78 @@
+//! Per-request identifier, taken from the `X-Request-Id` header when the
+//! caller sent a sensible one, and generated otherwise.
+
+use axum::extract::Request;
+use axum::http::{HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName =
+    HeaderName::from_static("x-request-id");
+
+/// Longest identifier accepted from a caller.
+const MAX_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Identifier of the request, found in its extensions.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Middleware assigning the request id, and echoing it in the response.
+/// It must wrap the `TraceLayer`, so that the request span can record it.
+pub async fn request_id(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid(id))
+        .map_or_else(|| Uuid::new_v4().to_string(), String::from);
+    let value = HeaderValue::from_str(&id).ok();
+
+    // Rewritten, so that an invalid id is not forwarded further
+    if let Some(value) = &value {
+        request
+            .headers_mut()
+            .insert(REQUEST_ID_HEADER, value.clone());
+    }
+    request.extensions_mut().insert(RequestId(id.clone()));
+
+    let mut response = REQUEST_ID.scope(id, next.run(request)).await;
+    if let Some(value) = value {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Id of the request being handled, outside of a request or of its task
+/// there is none.
+#[must_use]
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Ids end up in logs and pages, so only short, plain ones are kept.
+fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[tokio::test]
+async fn test() {
+    assert!(is_valid("cb910ce9-d611-4345-89f8-6399b836cf7b"));
+    assert!(!is_valid(""));
+    assert!(!is_valid("id\nINFO forged log line"));
+    assert!(!is_valid(&"a".repeat(MAX_LEN + 1)));
+
+    assert_eq!(current_request_id(), None);
+    let id = REQUEST_ID
+        .scope("abc".to_string(), async { current_request_id() })
+        .await;
+    assert_eq!(id.as_deref(), Some("abc"));
+}
@@ -0,0 +1,87 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+use tokio::time::{self, Instant};
+use tracing::{event, Level};
+
+/// When a shutdown hook runs, phases running in declaration order. Hooks of
+/// the same phase run in registration order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    /// Background jobs, so that they stop using connections.
+    Jobs,
+    /// Database pools and other client connections.
+    Connections,
+    /// Log and telemetry exporters, last so that they see everything else.
+    Telemetry,
+}
+
+type Hook =
+    Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+
+lazy_static! {
+    static ref HOOKS: Mutex<Vec<(Phase, &'static str, Hook)>> =
+        Mutex::new(Vec::new());
+}
+
+/// Registers `hook` to run once the server stopped serving requests, within
+/// what is left of the shutdown budget.
+///
+/// # Panics
+///
+/// Panics when another thread panicked while registering a hook.
+pub fn on_shutdown<F, Fut>(phase: Phase, name: &'static str, hook: F)
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let hook: Hook = Box::new(move || Box::pin(hook()));
+    HOOKS.lock().unwrap().push((phase, name, hook));
+}
+
+/// Runs every registered hook in order. A hook still running at `deadline`
+/// is abandoned, and the next ones are only polled once.
+///
+/// # Panics
+///
+/// Panics when another thread panicked while registering a hook.
+pub async fn run_shutdown_hooks(deadline: Instant) {
+    let mut hooks = std::mem::take(&mut *HOOKS.lock().unwrap());
+    hooks.sort_by_key(|(phase, ..)| *phase);
+
+    for (phase, name, hook) in hooks {
+        event!(Level::DEBUG, "Running {phase:?} shutdown hook `{name}`...");
+        if time::timeout_at(deadline, hook()).await.is_err() {
+            event!(
+                Level::WARN,
+                "Shutdown hook `{name}` did not finish within the shutdown \
+                 budget!"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn test() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let order = Arc::new(Mutex::new(Vec::new()));
+    for (phase, name) in [
+        (Phase::Telemetry, "logs"),
+        (Phase::Connections, "db"),
+        (Phase::Jobs, "sweeper"),
+        (Phase::Connections, "cache"),
+    ] {
+        let order = Arc::clone(&order);
+        on_shutdown(phase, name, move || async move {
+            order.lock().unwrap().push(name);
+        });
+    }
+    on_shutdown(Phase::Jobs, "stuck", || time::sleep(Duration::MAX));
+
+    run_shutdown_hooks(Instant::now() + Duration::from_millis(50)).await;
+    assert_eq!(*order.lock().unwrap(), ["sweeper", "db", "cache", "logs"]);
+}
@@ -0,0 +1,123 @@
+//! OpenTelemetry tracing, with W3C trace-context propagation.
+//!
+//! Spans always carry a trace id, so that logs and errors can be correlated
+//! with upstream traces. They are only exported when an OTLP endpoint is
+//! configured.
+
+use axum::http::HeaderMap;
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry::{global, Context};
+use opentelemetry_http::HeaderExtractor;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use tracing::{event, Level, Span, Subscriber};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+use environment::ENV;
+
+use crate::{on_shutdown, Phase};
+
+/// Builds the layer turning `tracing` spans into OpenTelemetry spans, and
+/// registers the shutdown hook flushing the spans not exported yet.
+///
+/// # Panics
+///
+/// When the OTLP exporter fails to build.
+#[must_use]
+pub fn otel_layer<S>() -> OpenTelemetryLayer<S, SdkTracer>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let config = &ENV.config.telemetry;
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    // Traces started upstream keep their sampling decision
+    let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+        config.sampling_ratio,
+    )));
+    let resource = Resource::builder()
+        .with_service_name(config.service_name.clone())
+        .build();
+    let mut builder = SdkTracerProvider::builder()
+        .with_sampler(sampler)
+        .with_resource(resource);
+
+    if let Some(endpoint) = &config.otlp_endpoint {
+        let endpoint = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(&endpoint)
+            .build()
+            .unwrap_or_else(|e| panic!("Failed to build OTLP exporter: {e}"));
+        builder = builder.with_batch_exporter(exporter);
+    }
+
+    let provider = builder.build();
+    let tracer = provider.tracer("cheesecake");
+    global::set_tracer_provider(provider.clone());
+
+    on_shutdown(Phase::Telemetry, "telemetry", move || async move {
+        // Exporting is blocking, it runs on its own thread
+        let result =
+            tokio::task::spawn_blocking(move || provider.shutdown()).await;
+        if let Ok(Err(e)) = result {
+            event!(Level::ERROR, "Failed to flush spans! Error: {e}");
+        }
+    });
+
+    tracing_opentelemetry::layer().with_tracer(tracer)
+}
+
+/// Makes `span` part of the trace of the incoming `traceparent` header, if
+/// any.
+pub fn set_parent_from(span: &Span, headers: &HeaderMap) {
+    let parent: Context = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    });
+    if parent.has_active_span() {
+        // Only fails when the span is disabled, so not exported anyway
+        let _ = span.set_parent(parent);
+    }
+}
+
+/// Trace id of the current span, if it is part of a trace.
+#[must_use]
+pub fn current_trace_id() -> Option<String> {
+    let context = Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_string())
+}
+
+#[test]
+fn test() {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "traceparent",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+            .parse()
+            .unwrap(),
+    );
+
+    let subscriber = tracing_subscriber::layer::SubscriberExt::with(
+        tracing_subscriber::registry(),
+        tracing_opentelemetry::layer()
+            .with_tracer(SdkTracerProvider::builder().build().tracer("test")),
+    );
+    tracing::subscriber::with_default(subscriber, || {
+        assert_eq!(current_trace_id(), None);
+        let span = tracing::info_span!("request");
+        set_parent_from(&span, &headers);
+        let _entered = span.enter();
+        assert_eq!(
+            current_trace_id().as_deref(),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+    });
+}
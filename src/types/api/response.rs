@@ -11,6 +11,8 @@ pub struct Response<T> {
 pub struct ErrorLog {
     pub message: String,
     pub identifier: Option<String>,
+    /// `X-Request-Id` of the failed request, see `with_request_id`.
+    pub request_id: Option<String>,
     pub time: String,
 }
 
@@ -27,6 +29,7 @@ impl<T> Response<T> {
         let error_log = ErrorLog {
             message,
             identifier: identifier.map(|i| i.to_string()),
+            request_id: None,
             time: chrono::Utc::now()
                 .to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
         };
@@ -35,6 +38,24 @@ impl<T> Response<T> {
             error: Some(error_log),
         }
     }
+
+    /// Sets the `X-Request-Id` of an error, see `utils::request_id`.
+    #[must_use]
+    pub fn with_request_id(mut self, request_id: Option<String>) -> Self {
+        if let Some(error) = &mut self.error {
+            error.request_id = request_id;
+        }
+        self
+    }
+
+    /// An error that still carries data, e.g. to explain what failed.
+    #[must_use]
+    pub fn error_with(message: String, data: T) -> Self {
+        Self {
+            data: Some(data),
+            ..Self::error(message, None)
+        }
+    }
 }
 
 impl<T> From<T> for Response<T> {
@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+use super::Entity;
+
+/// A personal access token of a user, sent as `Authorization: Bearer`.
+///
+/// Only the hash is stored, the token itself is shown once on creation.
+#[derive(Clone, FromRow, Entity)]
+#[entity(table = "api_tokens")]
+pub struct ApiToken {
+    #[entity(generated)]
+    pub id: i64,
+    pub user_id: i64,
+    /// Chosen by the user, to tell their tokens apart.
+    pub name: String,
+    /// SHA-256 of the token.
+    pub token_hash: Vec<u8>,
+    /// Permissions of the user the token may be used for, see
+    /// `controllers::extractors::RequireScope`.
+    pub scopes: Vec<String>,
+    #[entity(generated)]
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// Never expires when `None`.
+    pub expires_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,73 @@
+use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::query::QueryAs;
+use sqlx::{Encode, FromRow, Postgres, Type};
+
+pub use entity_derive::Entity;
+
+/// Query an entity binds its values to.
+pub type EntityQuery<'q, E> = QueryAs<'q, Postgres, E, PgArguments>;
+
+/// A struct mapped to a table, used by `repositories::Repository`.
+///
+/// Derive it along with `sqlx::FromRow`:
+/// - `#[entity(table = "...")]` on the struct names the table, which
+///   otherwise is the struct name in `snake_case`.
+/// - `#[entity(id)]` marks the primary key, which otherwise is the `id`
+///   field.
+/// - `#[entity(generated)]` marks columns filled by the database (serial
+///   ids, `DEFAULT now()`, ...), never written by `insert` or `update`.
+pub trait Entity: for<'r> FromRow<'r, PgRow> + Send + Sync + Unpin {
+    type Id: for<'q> Encode<'q, Postgres> + Type<Postgres> + Send + Sync;
+
+    const TABLE: &'static str;
+    const ID: &'static str;
+    /// Every column, the id included.
+    const COLUMNS: &'static [&'static str];
+    /// Columns written by `insert`, in `bind_insert` order.
+    const INSERT_COLUMNS: &'static [&'static str];
+    /// Columns written by `update`, in `bind_update` order.
+    const UPDATE_COLUMNS: &'static [&'static str];
+
+    fn id(&self) -> &Self::Id;
+
+    /// Binds the values of `INSERT_COLUMNS`.
+    fn bind_insert<'q>(
+        &'q self,
+        query: EntityQuery<'q, Self>,
+    ) -> EntityQuery<'q, Self>;
+
+    /// Binds the values of `UPDATE_COLUMNS`.
+    fn bind_update<'q>(
+        &'q self,
+        query: EntityQuery<'q, Self>,
+    ) -> EntityQuery<'q, Self>;
+}
+
+#[test]
+fn test() {
+    #[derive(FromRow, Entity)]
+    #[entity(table = "people")]
+    struct Person {
+        #[entity(generated)]
+        id: i64,
+        name: String,
+    }
+
+    #[derive(FromRow, Entity)]
+    struct UserRole {
+        #[entity(id)]
+        key: String,
+        role: String,
+    }
+
+    assert_eq!(Person::TABLE, "people");
+    assert_eq!(Person::ID, "id");
+    assert_eq!(Person::COLUMNS, ["id", "name"]);
+    assert_eq!(Person::INSERT_COLUMNS, ["name"]);
+    assert_eq!(Person::UPDATE_COLUMNS, ["name"]);
+
+    assert_eq!(UserRole::TABLE, "user_role");
+    assert_eq!(UserRole::ID, "key");
+    assert_eq!(UserRole::INSERT_COLUMNS, ["key", "role"]);
+    assert_eq!(UserRole::UPDATE_COLUMNS, ["role"]);
+}
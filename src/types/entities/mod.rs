@@ -1 +1,17 @@
+mod entity;
+pub use entity::*;
 
+mod session;
+pub use session::*;
+
+mod user;
+pub use user::*;
+
+mod role;
+pub use role::*;
+
+mod permission;
+pub use permission::*;
+
+mod api_token;
+pub use api_token::*;
@@ -0,0 +1,13 @@
+use sqlx::FromRow;
+
+use super::Entity;
+
+/// A permission, given to roles. Checked by name, see
+/// `controllers::extractors::RequirePermission`.
+#[derive(Debug, Clone, FromRow, Entity)]
+#[entity(table = "permissions")]
+pub struct PermissionRecord {
+    #[entity(generated)]
+    pub id: i64,
+    pub name: String,
+}
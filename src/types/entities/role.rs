@@ -0,0 +1,12 @@
+use sqlx::FromRow;
+
+use super::Entity;
+
+/// A set of permissions, given to users.
+#[derive(Debug, Clone, FromRow, Entity)]
+#[entity(table = "roles")]
+pub struct Role {
+    #[entity(generated)]
+    pub id: i64,
+    pub name: String,
+}
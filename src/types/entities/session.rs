@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use sqlx::types::Json;
+use sqlx::FromRow;
+
+use super::Entity;
+
+/// Server-side session data, looked up by the id in the session cookie.
+#[derive(Debug, Clone, FromRow, Entity)]
+#[entity(table = "sessions")]
+pub struct SessionRecord {
+    pub id: String,
+    pub data: Json<Map<String, Value>>,
+    pub created_at: DateTime<Utc>,
+    /// The earliest of the idle and the absolute timeout.
+    pub expires_at: DateTime<Utc>,
+}
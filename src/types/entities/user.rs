@@ -0,0 +1,21 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+use super::Entity;
+
+/// An account, signed in with its email and password.
+///
+/// Not `Debug` nor `Serialize`, so that the hash cannot leak into logs or
+/// responses by accident.
+#[derive(Clone, FromRow, Entity)]
+#[entity(table = "users")]
+pub struct User {
+    #[entity(generated)]
+    pub id: i64,
+    /// Trimmed and lowercase.
+    pub email: String,
+    /// Argon2id, in the PHC string format.
+    pub password_hash: String,
+    #[entity(generated)]
+    pub created_at: DateTime<Utc>,
+}
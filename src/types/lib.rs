@@ -1,2 +1,5 @@
+// `#[derive(Entity)]` refers to `::types`, also from within this crate
+extern crate self as types;
+
 pub mod api;
 pub mod entities;
@@ -9,6 +9,7 @@ struct Template {
     message: String,
     status_code: u16,
     id: Option<String>,
+    request_id: Option<String>,
     curr_date: String,
     show_back_anchor: bool,
 }
@@ -18,12 +19,14 @@ pub fn render(
     status_code: u16,
     message: String,
     id: Option<String>,
+    request_id: Option<String>,
     show_back_anchor: bool,
 ) -> String {
     let templ = Template {
         message,
         status_code,
         id,
+        request_id,
         curr_date: chrono::Local::now().to_rfc2822(),
         show_back_anchor,
     };
@@ -42,6 +45,7 @@ fn test() {
     let id = Some("cb910ce9-d611-4345-89f8-6399b836cf7b".to_string());
     let tmpl = Template {
         id,
+        request_id: Some("req-1".to_string()),
         ..Default::default()
     };
     assert!(tmpl.render("error.html").is_ok());
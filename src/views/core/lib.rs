@@ -4,7 +4,9 @@ pub mod error;
 pub mod footer;
 pub mod header;
 pub mod index;
+pub mod login;
 pub mod not_found;
+pub mod register;
 
 use std::convert::identity;
 #[cfg(debug_assertions)]
@@ -17,6 +19,7 @@ use anyhow::Result;
 use lazy_static::lazy_static;
 #[cfg(not(debug_assertions))]
 use minify_html::{minify, Cfg};
+use prometheus::{register_histogram_vec, HistogramVec};
 use serde::Serialize;
 use tera::Tera;
 use tracing::{event, Level};
@@ -53,11 +56,9 @@ lazy_static! {
         let mut hotwatch =
             Hotwatch::new_with_custom_delay(Duration::new(1, 0)).unwrap();
         hotwatch
-            .watch(templates_dir!(), |event: Event| {
-                match event.kind {
-                    EventKind::Any | EventKind::Other => (),
-                    _ => drop(TERA.write().unwrap().full_reload()),
-                };
+            .watch(templates_dir!(), |event: Event| match event.kind {
+                EventKind::Any | EventKind::Other => (),
+                _ => drop(TERA.write().unwrap().full_reload()),
             })
             .unwrap();
 
@@ -65,6 +66,15 @@ lazy_static! {
     };
 }
 
+lazy_static! {
+    static ref RENDER_DURATION: HistogramVec = register_histogram_vec!(
+        "template_render_duration_seconds",
+        "Time to render each template, minification included",
+        &["template"]
+    )
+    .unwrap();
+}
+
 pub trait AppTemplate: Serialize + Default {
     /// Renders the template with given path/name
     ///
@@ -75,6 +85,29 @@ pub trait AppTemplate: Serialize + Default {
     fn render(self, path: &'static str) -> Result<String>;
 }
 
+/// Whether the templates were parsed, parsing them on the first call.
+///
+/// # Panics
+///
+/// Panics when the templates cannot be parsed.
+#[cfg(debug_assertions)]
+#[must_use]
+pub fn templates_loaded() -> bool {
+    TERA.read()
+        .is_ok_and(|tera| tera.get_template_names().next().is_some())
+}
+
+/// Whether the templates were parsed, parsing them on the first call.
+///
+/// # Panics
+///
+/// Panics when the templates cannot be parsed.
+#[cfg(not(debug_assertions))]
+#[must_use]
+pub fn templates_loaded() -> bool {
+    TERA.get_template_names().next().is_some()
+}
+
 #[cfg(debug_assertions)]
 pub fn setup_hotwatch() {
     let _ = &*HOTWATCH;
@@ -118,6 +151,7 @@ impl<T: Serialize + Default> AppTemplate for T {
 
         event!(Level::DEBUG, "render context: {:?}", ctx);
 
+        let _timer = RENDER_DURATION.with_label_values(&[path]).start_timer();
         render_internal(path, ctx)
     }
 }
@@ -0,0 +1,41 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use super::AppTemplate;
+
+#[derive(Serialize, Default)]
+struct Template {
+    header: String,
+    footer: String,
+    email: String,
+    error: Option<String>,
+    /// Where to go once logged in.
+    next: Option<String>,
+}
+
+pub fn render(
+    email: &str,
+    error: Option<&str>,
+    next: Option<&str>,
+) -> Result<String> {
+    Template {
+        header: super::header::render()?,
+        footer: super::footer::render()?,
+        email: email.to_string(),
+        error: error.map(String::from),
+        next: next.map(String::from),
+    }
+    .render("login.html")
+}
+
+#[test]
+fn test() {
+    assert!(Template::default().render("login.html").is_ok());
+    let tmpl = Template {
+        email: "ada@example.com".to_string(),
+        error: Some("Invalid email or password".to_string()),
+        next: Some("/account".to_string()),
+        ..Default::default()
+    };
+    assert!(tmpl.render("login.html").is_ok());
+}
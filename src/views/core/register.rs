@@ -0,0 +1,32 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use super::AppTemplate;
+
+#[derive(Serialize, Default)]
+struct Template {
+    header: String,
+    footer: String,
+    email: String,
+    errors: Vec<String>,
+}
+
+pub fn render(email: &str, errors: Vec<String>) -> Result<String> {
+    Template {
+        header: super::header::render()?,
+        footer: super::footer::render()?,
+        email: email.to_string(),
+        errors,
+    }
+    .render("register.html")
+}
+
+#[test]
+fn test() {
+    assert!(Template::default().render("register.html").is_ok());
+    let tmpl = Template {
+        errors: vec!["This email is already registered".to_string()],
+        ..Default::default()
+    };
+    assert!(tmpl.render("register.html").is_ok());
+}